@@ -1,22 +1,65 @@
 use crate::raw::*;
 use crate::Result;
 use std::collections::HashMap;
+use std::time::Duration;
 
 // Ts3 uses just whatever is available in the DB system, could be i32 or i64, though every foreign key is unsigned..
 pub type ServerId = u64;
+#[cfg(not(feature = "strict-ids"))]
 pub type ServerGroupID = u64;
+#[cfg(feature = "strict-ids")]
+pub type ServerGroupID = crate::ids::ServerGroupId;
+#[cfg(not(feature = "strict-ids"))]
 pub type ChannelId = u64;
+#[cfg(feature = "strict-ids")]
+pub type ChannelId = crate::ids::ChannelId;
 /// Temporary, per connection ID of a client, reused upon disconnect.  
 /// Not to be confused with a client database, myteamspeak or identity ID.
+#[cfg(not(feature = "strict-ids"))]
 pub type ClientId = u16;
+#[cfg(feature = "strict-ids")]
+pub type ClientId = crate::ids::ClientId;
 /// Server interal ID for client, not it's Identity / MyTeamspeak ID.
+#[cfg(not(feature = "strict-ids"))]
 pub type ClientDBId = u64;
+#[cfg(feature = "strict-ids")]
+pub type ClientDBId = crate::ids::ClientDbId;
 pub type ChannelGroupId = u64;
+/// Numeric permission ID, resolvable from its name via `permidgetbyname`
+pub type PermissionId = i32;
 /// CRC32 checksum of the channel icon, but received as i64 instead of u64, except when using `clientdbinfo`
 pub type IconHash = i64;
 
+macro_rules! from_query_string {
+    ($($name:ident),*) => {
+        $(
+            impl FromQueryString for $name {
+                fn from_query(data: HashMap<String, Option<String>>) -> Result<Self> {
+                    Self::from_raw(data)
+                }
+            }
+        )*
+    };
+}
+
+from_query_string!(
+    ServerGroup,
+    OnlineClient,
+    OnlineClientFull,
+    QueryLogin,
+    ClientDbEntry,
+    PrivilegeKey,
+    Permission,
+    GroupPerm,
+    PermOverview,
+    FileEntry,
+    ApiKey,
+    Channel,
+    ChannelFull
+);
+
 /// Server Group returned from `server_group_list`. Field names are according to the query protocol.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ServerGroup {
     /// Identifier for this server group
     pub sgid: ServerGroupID,
@@ -48,7 +91,7 @@ impl ServerGroup {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct OnlineClient {
     pub clid: ClientId,
     pub cid: ChannelId,
@@ -105,8 +148,10 @@ pub struct OnlineClientFull {
     pub client_idle_time: i64,
     pub client_created: i64,
     pub client_lastconnected: i64,
-    pub client_country: String,
-    pub connection_client_ip: String,
+    /// Absent or empty if unknown
+    pub client_country: Option<String>,
+    /// Absent without `b_client_remoteaddress_view`
+    pub connection_client_ip: Option<String>,
     pub client_badges: Option<String>, // TODO: CHECK TYPE
 }
 
@@ -141,8 +186,16 @@ impl OnlineClientFull {
         let client_idle_time = int_val_parser(&mut data, "client_idle_time")?;
         let client_created = int_val_parser(&mut data, "client_created")?;
         let client_lastconnected = int_val_parser(&mut data, "client_lastconnected")?;
-        let client_country = string_val_parser(&mut data, "client_country")?;
-        let connection_client_ip = string_val_parser(&mut data, "connection_client_ip")?;
+        let client_country = data
+            .remove("client_country")
+            .flatten()
+            .filter(|v| !v.is_empty())
+            .map(unescape_val);
+        let connection_client_ip = data
+            .remove("connection_client_ip")
+            .flatten()
+            .filter(|v| !v.is_empty())
+            .map(unescape_val);
         let client_badges = string_val_parser_opt(&mut data, "client_badges")?;
 
         Ok(OnlineClientFull {
@@ -179,7 +232,841 @@ impl OnlineClientFull {
     }
 }
 
+/// Query login returned from `queryloginlist`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryLogin {
+    pub cldbid: ClientDBId,
+    pub client_login_name: String,
+}
+
+impl QueryLogin {
+    pub(crate) fn from_raw(mut data: HashMap<String, Option<String>>) -> Result<Self> {
+        let cldbid = int_val_parser(&mut data, "cldbid")?;
+        let client_login_name = string_val_parser(&mut data, "client_login_name")?;
+
+        Ok(QueryLogin {
+            cldbid,
+            client_login_name,
+        })
+    }
+}
+
+/// Client database entry returned from `clientdblist`
+#[derive(Debug)]
+pub struct ClientDbEntry {
+    pub cldbid: ClientDBId,
+    pub client_unique_identifier: String,
+    pub client_nickname: String,
+    pub client_created: i64,
+    pub client_lastconnected: i64,
+    pub client_totalconnections: i32,
+    pub client_description: Option<String>,
+    pub client_lastip: Option<String>,
+}
+
+impl ClientDbEntry {
+    pub(crate) fn from_raw(mut data: HashMap<String, Option<String>>) -> Result<Self> {
+        let cldbid = int_val_parser(&mut data, "cldbid")?;
+        let client_unique_identifier = string_val_parser(&mut data, "client_unique_identifier")?;
+        let client_nickname = string_val_parser(&mut data, "client_nickname")?;
+        let client_created = int_val_parser(&mut data, "client_created")?;
+        let client_lastconnected = int_val_parser(&mut data, "client_lastconnected")?;
+        let client_totalconnections = int_val_parser(&mut data, "client_totalconnections")?;
+        let client_description = string_val_parser_opt(&mut data, "client_description")?;
+        let client_lastip = string_val_parser_opt(&mut data, "client_lastip")?;
+
+        Ok(ClientDbEntry {
+            cldbid,
+            client_unique_identifier,
+            client_nickname,
+            client_created,
+            client_lastconnected,
+            client_totalconnections,
+            client_description,
+            client_lastip,
+        })
+    }
+}
+
+/// Editable client database properties, used for `clientdbedit`.
+/// Values that are `None` are not changed.
+#[derive(Debug, Default)]
+pub struct ClientDbEdit {
+    pub client_description: Option<String>,
+    pub client_icon_id: Option<IconHash>,
+}
+
+impl ClientDbEdit {
+    pub(crate) fn to_raw(&self) -> String {
+        let mut result = String::new();
+
+        if let Some(x) = &self.client_description {
+            result += &format!(" client_description={}", &escape_arg(x));
+        }
+        if let Some(x) = self.client_icon_id {
+            result += &format!(" client_icon_id={}", x);
+        }
+
+        result
+    }
+}
+
+/// Common instance properties, used for `instanceedit` via [`props`](InstanceEdit::props).
+/// Values that are `None` are not changed.
+#[derive(Debug, Default)]
+pub struct InstanceEdit {
+    pub serverinstance_filetransfer_port: Option<u16>,
+    pub serverinstance_guest_serverquery_group: Option<ServerGroupID>,
+    pub serverinstance_template_serveradmin_group: Option<ServerGroupID>,
+    pub serverinstance_template_serverdefault_group: Option<ServerGroupID>,
+    pub serverinstance_max_download_total_bandwidth: Option<u64>,
+    pub serverinstance_max_upload_total_bandwidth: Option<u64>,
+    pub serverinstance_serverquery_flood_commands: Option<u32>,
+    pub serverinstance_serverquery_flood_time: Option<u32>,
+    pub serverinstance_serverquery_ban_time: Option<u32>,
+}
+
+impl InstanceEdit {
+    /// Returns the set properties as key/value pairs for [`instance_edit`](crate::QueryClient::instance_edit)
+    pub fn props(&self) -> Vec<(&'static str, String)> {
+        let mut result = Vec::new();
+        let mut add = |key, value: Option<String>| {
+            if let Some(v) = value {
+                result.push((key, v));
+            }
+        };
+        add(
+            "serverinstance_filetransfer_port",
+            self.serverinstance_filetransfer_port.map(|v| v.to_string()),
+        );
+        add(
+            "serverinstance_guest_serverquery_group",
+            self.serverinstance_guest_serverquery_group
+                .map(|v| v.to_string()),
+        );
+        add(
+            "serverinstance_template_serveradmin_group",
+            self.serverinstance_template_serveradmin_group
+                .map(|v| v.to_string()),
+        );
+        add(
+            "serverinstance_template_serverdefault_group",
+            self.serverinstance_template_serverdefault_group
+                .map(|v| v.to_string()),
+        );
+        add(
+            "serverinstance_max_download_total_bandwidth",
+            self.serverinstance_max_download_total_bandwidth
+                .map(|v| v.to_string()),
+        );
+        add(
+            "serverinstance_max_upload_total_bandwidth",
+            self.serverinstance_max_upload_total_bandwidth
+                .map(|v| v.to_string()),
+        );
+        add(
+            "serverinstance_serverquery_flood_commands",
+            self.serverinstance_serverquery_flood_commands
+                .map(|v| v.to_string()),
+        );
+        add(
+            "serverinstance_serverquery_flood_time",
+            self.serverinstance_serverquery_flood_time
+                .map(|v| v.to_string()),
+        );
+        add(
+            "serverinstance_serverquery_ban_time",
+            self.serverinstance_serverquery_ban_time
+                .map(|v| v.to_string()),
+        );
+        result
+    }
+}
+
+/// Privilege key returned from `privilegekeylist`
+#[derive(Debug)]
+pub struct PrivilegeKey {
+    pub token: String,
+    /// 0 for server group, 1 for channel group
+    pub token_type: i32,
+    /// Server or channel group ID
+    pub token_id1: u64,
+    /// Channel ID for channel groups, 0 otherwise
+    pub token_id2: ChannelId,
+    /// Creation time as unix timestamp
+    pub token_created: i64,
+    pub token_description: Option<String>,
+}
+
+impl PrivilegeKey {
+    pub(crate) fn from_raw(mut data: HashMap<String, Option<String>>) -> Result<Self> {
+        Ok(PrivilegeKey {
+            token: string_val_parser(&mut data, "token")?,
+            token_type: int_val_parser(&mut data, "token_type")?,
+            token_id1: int_val_parser(&mut data, "token_id1")?,
+            token_id2: int_val_parser(&mut data, "token_id2")?,
+            token_created: int_val_parser(&mut data, "token_created")?,
+            token_description: string_val_parser_opt(&mut data, "token_description")?,
+        })
+    }
+}
+
+/// Permission returned from `permissionlist`
+#[derive(Debug)]
+pub struct Permission {
+    pub permid: PermissionId,
+    /// Permission name, like `b_serverinstance_help_view`
+    pub permname: String,
+    pub permdesc: Option<String>,
+}
+
+impl Permission {
+    pub(crate) fn from_raw(mut data: HashMap<String, Option<String>>) -> Result<Self> {
+        let permid = int_val_parser(&mut data, "permid")?;
+        let permname = string_val_parser(&mut data, "permname")?;
+        let permdesc = string_val_parser_opt(&mut data, "permdesc")?;
+
+        Ok(Permission {
+            permid,
+            permname,
+            permdesc,
+        })
+    }
+}
+
+/// Permission identifier, either numeric or by name (`permsid`)
+#[derive(Debug)]
+pub enum PermIdent {
+    Id(PermissionId),
+    /// Permission name, like `i_channel_needed_join_power`
+    Name(String),
+}
+
+/// Permission of a group, returned from `servergrouppermlist` and `channelgrouppermlist`
+#[derive(Debug)]
+pub struct GroupPerm {
+    /// `permid` or `permsid`, depending on the request
+    pub perm: PermIdent,
+    pub permvalue: i32,
+    pub permnegated: bool,
+    pub permskip: bool,
+}
+
+impl GroupPerm {
+    pub(crate) fn from_raw(mut data: HashMap<String, Option<String>>) -> Result<Self> {
+        let perm = if data.contains_key("permsid") {
+            PermIdent::Name(string_val_parser(&mut data, "permsid")?)
+        } else {
+            PermIdent::Id(int_val_parser(&mut data, "permid")?)
+        };
+        let permvalue = int_val_parser(&mut data, "permvalue")?;
+        let permnegated = bool_val_parser(&mut data, "permnegated")?;
+        let permskip = bool_val_parser(&mut data, "permskip")?;
+
+        Ok(GroupPerm {
+            perm,
+            permvalue,
+            permnegated,
+            permskip,
+        })
+    }
+}
+
+/// Effective permission entry of a client, returned from `permoverview`
+#[derive(Debug)]
+pub struct PermOverview {
+    /// Source of the permission:
+    /// 0 server group, 1 client, 2 channel, 3 channel group, 4 channel client
+    pub t: i32,
+    /// First ID of the source, for example the server group or channel ID
+    pub id1: u64,
+    /// Second ID of the source, for example the client database ID for channel client permissions
+    pub id2: u64,
+    pub permid: PermissionId,
+    pub value: i32,
+    pub negated: bool,
+    pub skip: bool,
+}
+
+impl PermOverview {
+    pub(crate) fn from_raw(mut data: HashMap<String, Option<String>>) -> Result<Self> {
+        Ok(PermOverview {
+            t: int_val_parser(&mut data, "t")?,
+            id1: int_val_parser(&mut data, "id1")?,
+            id2: int_val_parser(&mut data, "id2")?,
+            permid: int_val_parser(&mut data, "p")?,
+            value: int_val_parser(&mut data, "v")?,
+            negated: bool_val_parser(&mut data, "n")?,
+            skip: bool_val_parser(&mut data, "s")?,
+        })
+    }
+}
+
+/// File or directory entry returned from `ftgetfilelist` and `ftgetfileinfo`
+#[derive(Debug)]
+pub struct FileEntry {
+    pub name: String,
+    /// Size in bytes
+    pub size: u64,
+    /// Last modification as unix timestamp
+    pub datetime: i64,
+    /// 0 for directory, 1 for file
+    pub r#type: i32,
+}
+
+impl FileEntry {
+    pub(crate) fn from_raw(mut data: HashMap<String, Option<String>>) -> Result<Self> {
+        let name = string_val_parser(&mut data, "name")?;
+        let size = int_val_parser(&mut data, "size")?;
+        let datetime = int_val_parser(&mut data, "datetime")?;
+        // ftgetfileinfo returns no type, only works on files
+        let r#type = if data.contains_key("type") {
+            int_val_parser(&mut data, "type")?
+        } else {
+            1
+        };
+
+        Ok(FileEntry {
+            name,
+            size,
+            datetime,
+            r#type,
+        })
+    }
+}
+
+/// File transfer details returned from `ftinitdownload` and `ftinitupload`.
+///
+/// Use `ftkey` to authenticate on the file transfer `port`.
+#[derive(Debug)]
+pub struct FileTransfer {
+    pub clientftfid: u16,
+    pub serverftfid: u16,
+    pub ftkey: String,
+    pub port: u16,
+    /// File size, only set for downloads
+    pub size: Option<u64>,
+    /// Position to resume from, only set for uploads
+    pub seekpos: Option<u64>,
+}
+
+impl FileTransfer {
+    pub(crate) fn from_raw(mut data: HashMap<String, Option<String>>) -> Result<Self> {
+        // transfer errors are reported inline, with an ok error line
+        if data.contains_key("status") {
+            let status = int_val_parser(&mut data, "status")?;
+            if status != 0 {
+                return crate::ServerError {
+                    response: ErrorResponse {
+                        id: status,
+                        msg: data
+                            .remove("msg")
+                            .flatten()
+                            .map(unescape_val)
+                            .unwrap_or_default(),
+                    },
+                }
+                .fail();
+            }
+        }
+        let clientftfid = int_val_parser(&mut data, "clientftfid")?;
+        let serverftfid = int_val_parser(&mut data, "serverftfid")?;
+        let ftkey = string_val_parser(&mut data, "ftkey")?;
+        let port = int_val_parser(&mut data, "port")?;
+        let size = if data.contains_key("size") {
+            int_val_parser_opt(&mut data, "size")?
+        } else {
+            None
+        };
+        let seekpos = if data.contains_key("seekpos") {
+            int_val_parser_opt(&mut data, "seekpos")?
+        } else {
+            None
+        };
+
+        Ok(FileTransfer {
+            clientftfid,
+            serverftfid,
+            ftkey,
+            port,
+            size,
+            seekpos,
+        })
+    }
+}
+
+/// Log entries returned from `logview`
+#[derive(Debug)]
+pub struct LogView {
+    /// Position to continue reading older entries from, pass as `begin_pos`. 0 if everything was read.
+    pub last_pos: u64,
+    /// Size of the log file in bytes
+    pub file_size: u64,
+    /// Log lines, unescaped
+    pub lines: Vec<String>,
+}
+
+impl LogView {
+    pub(crate) fn from_raw(data: Vec<HashMap<String, Option<String>>>) -> Result<Self> {
+        let mut data = data.into_iter();
+        let (last_pos, file_size, first) = match data.next() {
+            Some(mut first) => (
+                int_val_parser(&mut first, "last_pos")?,
+                int_val_parser(&mut first, "file_size")?,
+                // an empty log has no `l` entry in the first record
+                match string_val_parser_opt(&mut first, "l") {
+                    Ok(v) => v,
+                    Err(crate::Ts3Error::NoEntryResponse { .. }) => None,
+                    Err(e) => return Err(e),
+                },
+            ),
+            None => (0, 0, None),
+        };
+        let lines = first
+            .into_iter()
+            .map(Ok)
+            .chain(data.map(|mut v| string_val_parser(&mut v, "l")))
+            .collect::<Result<_>>()?;
+
+        Ok(LogView {
+            last_pos,
+            file_size,
+            lines,
+        })
+    }
+}
+
+/// Information about the selected virtual server, returned from `serverinfo`
+#[derive(Debug)]
+pub struct ServerInfo {
+    pub virtualserver_id: ServerId,
+    pub virtualserver_name: String,
+    pub virtualserver_port: u16,
+    pub virtualserver_platform: String,
+    pub virtualserver_version: String,
+    pub virtualserver_clientsonline: i32,
+    pub virtualserver_queryclientsonline: i32,
+    pub virtualserver_maxclients: i32,
+    /// Uptime in seconds, see [`uptime`](ServerInfo::uptime)
+    pub virtualserver_uptime: u64,
+}
+
+impl ServerInfo {
+    pub(crate) fn from_raw(mut data: HashMap<String, Option<String>>) -> Result<Self> {
+        Ok(ServerInfo {
+            virtualserver_id: int_val_parser(&mut data, "virtualserver_id")?,
+            virtualserver_name: string_val_parser(&mut data, "virtualserver_name")?,
+            virtualserver_port: int_val_parser(&mut data, "virtualserver_port")?,
+            virtualserver_platform: string_val_parser(&mut data, "virtualserver_platform")?,
+            virtualserver_version: string_val_parser(&mut data, "virtualserver_version")?,
+            virtualserver_clientsonline: int_val_parser(&mut data, "virtualserver_clientsonline")?,
+            virtualserver_queryclientsonline: int_val_parser(
+                &mut data,
+                "virtualserver_queryclientsonline",
+            )?,
+            virtualserver_maxclients: int_val_parser(&mut data, "virtualserver_maxclients")?,
+            virtualserver_uptime: int_val_parser(&mut data, "virtualserver_uptime")?,
+        })
+    }
+
+    /// Returns the uptime of the virtual server
+    pub fn uptime(&self) -> Duration {
+        Duration::from_secs(self.virtualserver_uptime)
+    }
+}
+
+/// Information about the server instance, returned from `hostinfo`
+#[derive(Debug)]
+pub struct HostInfo {
+    /// Uptime in seconds, see [`uptime`](HostInfo::uptime)
+    pub instance_uptime: u64,
+    /// Current server time as unix timestamp
+    pub host_timestamp_utc: i64,
+    pub virtualservers_running_total: i32,
+    pub virtualservers_total_maxclients: i32,
+    pub virtualservers_total_clients_online: i32,
+    pub virtualservers_total_channels_online: i32,
+}
+
+impl HostInfo {
+    pub(crate) fn from_raw(mut data: HashMap<String, Option<String>>) -> Result<Self> {
+        Ok(HostInfo {
+            instance_uptime: int_val_parser(&mut data, "instance_uptime")?,
+            host_timestamp_utc: int_val_parser(&mut data, "host_timestamp_utc")?,
+            virtualservers_running_total: int_val_parser(
+                &mut data,
+                "virtualservers_running_total",
+            )?,
+            virtualservers_total_maxclients: int_val_parser(
+                &mut data,
+                "virtualservers_total_maxclients",
+            )?,
+            virtualservers_total_clients_online: int_val_parser(
+                &mut data,
+                "virtualservers_total_clients_online",
+            )?,
+            virtualservers_total_channels_online: int_val_parser(
+                &mut data,
+                "virtualservers_total_channels_online",
+            )?,
+        })
+    }
+
+    /// Returns the uptime of the server instance
+    pub fn uptime(&self) -> Duration {
+        Duration::from_secs(self.instance_uptime)
+    }
+}
+
+/// Connection statistics of a virtual server, returned from `serverrequestconnectioninfo`
 #[derive(Debug)]
+pub struct ConnectionInfo {
+    pub connection_filetransfer_bandwidth_sent: i64,
+    pub connection_filetransfer_bandwidth_received: i64,
+    pub connection_filetransfer_bytes_sent_total: i64,
+    pub connection_filetransfer_bytes_received_total: i64,
+    pub connection_packets_sent_total: i64,
+    pub connection_bytes_sent_total: i64,
+    pub connection_packets_received_total: i64,
+    pub connection_bytes_received_total: i64,
+    pub connection_bandwidth_sent_last_second_total: i64,
+    pub connection_bandwidth_sent_last_minute_total: i64,
+    pub connection_bandwidth_received_last_second_total: i64,
+    pub connection_bandwidth_received_last_minute_total: i64,
+    /// Connected time in seconds
+    pub connection_connected_time: i64,
+    /// Packetloss as fraction
+    pub connection_packetloss_total: f64,
+    /// Ping in ms
+    pub connection_ping: f64,
+}
+
+impl ConnectionInfo {
+    pub(crate) fn from_raw(mut data: HashMap<String, Option<String>>) -> Result<Self> {
+        let connection_filetransfer_bandwidth_sent =
+            int_val_parser(&mut data, "connection_filetransfer_bandwidth_sent")?;
+        let connection_filetransfer_bandwidth_received =
+            int_val_parser(&mut data, "connection_filetransfer_bandwidth_received")?;
+        let connection_filetransfer_bytes_sent_total =
+            int_val_parser(&mut data, "connection_filetransfer_bytes_sent_total")?;
+        let connection_filetransfer_bytes_received_total =
+            int_val_parser(&mut data, "connection_filetransfer_bytes_received_total")?;
+        let connection_packets_sent_total =
+            int_val_parser(&mut data, "connection_packets_sent_total")?;
+        let connection_bytes_sent_total = int_val_parser(&mut data, "connection_bytes_sent_total")?;
+        let connection_packets_received_total =
+            int_val_parser(&mut data, "connection_packets_received_total")?;
+        let connection_bytes_received_total =
+            int_val_parser(&mut data, "connection_bytes_received_total")?;
+        let connection_bandwidth_sent_last_second_total =
+            int_val_parser(&mut data, "connection_bandwidth_sent_last_second_total")?;
+        let connection_bandwidth_sent_last_minute_total =
+            int_val_parser(&mut data, "connection_bandwidth_sent_last_minute_total")?;
+        let connection_bandwidth_received_last_second_total =
+            int_val_parser(&mut data, "connection_bandwidth_received_last_second_total")?;
+        let connection_bandwidth_received_last_minute_total =
+            int_val_parser(&mut data, "connection_bandwidth_received_last_minute_total")?;
+        let connection_connected_time = int_val_parser(&mut data, "connection_connected_time")?;
+        let connection_packetloss_total =
+            float_val_parser(&mut data, "connection_packetloss_total")?;
+        let connection_ping = float_val_parser(&mut data, "connection_ping")?;
+
+        Ok(ConnectionInfo {
+            connection_filetransfer_bandwidth_sent,
+            connection_filetransfer_bandwidth_received,
+            connection_filetransfer_bytes_sent_total,
+            connection_filetransfer_bytes_received_total,
+            connection_packets_sent_total,
+            connection_bytes_sent_total,
+            connection_packets_received_total,
+            connection_bytes_received_total,
+            connection_bandwidth_sent_last_second_total,
+            connection_bandwidth_sent_last_minute_total,
+            connection_bandwidth_received_last_second_total,
+            connection_bandwidth_received_last_minute_total,
+            connection_connected_time,
+            connection_packetloss_total,
+            connection_ping,
+        })
+    }
+}
+
+/// Connection statistics of a client, derived from `clientinfo`
+#[derive(Debug)]
+pub struct ClientConnectionInfo {
+    pub connection_filetransfer_bandwidth_sent: i64,
+    pub connection_filetransfer_bandwidth_received: i64,
+    pub connection_packets_sent_total: i64,
+    pub connection_bytes_sent_total: i64,
+    pub connection_packets_received_total: i64,
+    pub connection_bytes_received_total: i64,
+    pub connection_bandwidth_sent_last_second_total: i64,
+    pub connection_bandwidth_sent_last_minute_total: i64,
+    pub connection_bandwidth_received_last_second_total: i64,
+    pub connection_bandwidth_received_last_minute_total: i64,
+    /// Connected time in ms
+    pub connection_connected_time: i64,
+    /// Not visible without `b_client_remoteaddress_view`
+    pub connection_client_ip: Option<String>,
+    /// Packetloss as fraction, not reported by all server versions
+    pub connection_packetloss_total: Option<f64>,
+    /// Ping in ms, not reported by all server versions
+    pub connection_ping: Option<f64>,
+}
+
+impl ClientConnectionInfo {
+    pub(crate) fn from_raw(mut data: HashMap<String, Option<String>>) -> Result<Self> {
+        let connection_filetransfer_bandwidth_sent =
+            int_val_parser(&mut data, "connection_filetransfer_bandwidth_sent")?;
+        let connection_filetransfer_bandwidth_received =
+            int_val_parser(&mut data, "connection_filetransfer_bandwidth_received")?;
+        let connection_packets_sent_total =
+            int_val_parser(&mut data, "connection_packets_sent_total")?;
+        let connection_bytes_sent_total = int_val_parser(&mut data, "connection_bytes_sent_total")?;
+        let connection_packets_received_total =
+            int_val_parser(&mut data, "connection_packets_received_total")?;
+        let connection_bytes_received_total =
+            int_val_parser(&mut data, "connection_bytes_received_total")?;
+        let connection_bandwidth_sent_last_second_total =
+            int_val_parser(&mut data, "connection_bandwidth_sent_last_second_total")?;
+        let connection_bandwidth_sent_last_minute_total =
+            int_val_parser(&mut data, "connection_bandwidth_sent_last_minute_total")?;
+        let connection_bandwidth_received_last_second_total =
+            int_val_parser(&mut data, "connection_bandwidth_received_last_second_total")?;
+        let connection_bandwidth_received_last_minute_total =
+            int_val_parser(&mut data, "connection_bandwidth_received_last_minute_total")?;
+        let connection_connected_time = int_val_parser(&mut data, "connection_connected_time")?;
+        let connection_client_ip = data
+            .remove("connection_client_ip")
+            .flatten()
+            .map(unescape_val);
+        let connection_packetloss_total = if data.contains_key("connection_packetloss_total") {
+            Some(float_val_parser(&mut data, "connection_packetloss_total")?)
+        } else {
+            None
+        };
+        let connection_ping = if data.contains_key("connection_ping") {
+            Some(float_val_parser(&mut data, "connection_ping")?)
+        } else {
+            None
+        };
+
+        Ok(ClientConnectionInfo {
+            connection_filetransfer_bandwidth_sent,
+            connection_filetransfer_bandwidth_received,
+            connection_packets_sent_total,
+            connection_bytes_sent_total,
+            connection_packets_received_total,
+            connection_bytes_received_total,
+            connection_bandwidth_sent_last_second_total,
+            connection_bandwidth_sent_last_minute_total,
+            connection_bandwidth_received_last_second_total,
+            connection_bandwidth_received_last_minute_total,
+            connection_connected_time,
+            connection_client_ip,
+            connection_packetloss_total,
+            connection_ping,
+        })
+    }
+}
+
+/// Scope of a query API key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiScope {
+    Manage,
+    Write,
+    Read,
+}
+
+impl std::fmt::Display for ApiScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiScope::Manage => write!(f, "manage"),
+            ApiScope::Write => write!(f, "write"),
+            ApiScope::Read => write!(f, "read"),
+        }
+    }
+}
+
+impl ApiScope {
+    fn from_raw(data: &mut HashMap<String, Option<String>>) -> Result<Self> {
+        let scope = string_val_parser(data, "scope")?;
+        match scope.as_str() {
+            "manage" => Ok(ApiScope::Manage),
+            "write" => Ok(ApiScope::Write),
+            "read" => Ok(ApiScope::Read),
+            _ => crate::InvalidResponse {
+                context: "expected api scope, got ",
+                data: scope,
+            }
+            .fail(),
+        }
+    }
+}
+
+/// Group type of `servergroupautoaddperm`, matching the `i_group_auto_update_type` of groups
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoGroupType {
+    ChannelGuest,
+    ServerGuest,
+    QueryGuest,
+    ChannelVoice,
+    ServerNormal,
+    ChannelOperator,
+    ChannelAdmin,
+    ServerAdmin,
+    QueryAdmin,
+}
+
+impl AutoGroupType {
+    pub fn to_raw(self) -> i32 {
+        match self {
+            AutoGroupType::ChannelGuest => 10,
+            AutoGroupType::ServerGuest => 15,
+            AutoGroupType::QueryGuest => 20,
+            AutoGroupType::ChannelVoice => 25,
+            AutoGroupType::ServerNormal => 30,
+            AutoGroupType::ChannelOperator => 35,
+            AutoGroupType::ChannelAdmin => 40,
+            AutoGroupType::ServerAdmin => 45,
+            AutoGroupType::QueryAdmin => 50,
+        }
+    }
+}
+
+impl std::fmt::Display for AutoGroupType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_raw())
+    }
+}
+
+/// Reason of client moves, kicks and channel/server edits, received as `reasonid`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReasonId {
+    /// Client switched channels or connected
+    None,
+    /// Moved by another client
+    Moved,
+    /// Channel became (in)visible by (un)subscribing
+    Subscription,
+    /// Connection timed out
+    Timeout,
+    ChannelKick,
+    ServerKick,
+    Ban,
+    ServerStopped,
+    /// Client disconnected
+    Disconnect,
+    ChannelUpdated,
+    ChannelEdited,
+    /// Client disconnected due to server shutdown
+    ServerShutdown,
+    /// Unknown reason ID
+    Other(i32),
+}
+
+impl ReasonId {
+    pub fn from_raw(id: i32) -> Self {
+        match id {
+            0 => ReasonId::None,
+            1 => ReasonId::Moved,
+            2 => ReasonId::Subscription,
+            3 => ReasonId::Timeout,
+            4 => ReasonId::ChannelKick,
+            5 => ReasonId::ServerKick,
+            6 => ReasonId::Ban,
+            7 => ReasonId::ServerStopped,
+            8 => ReasonId::Disconnect,
+            9 => ReasonId::ChannelUpdated,
+            10 => ReasonId::ChannelEdited,
+            11 => ReasonId::ServerShutdown,
+            v => ReasonId::Other(v),
+        }
+    }
+
+    pub fn to_raw(self) -> i32 {
+        match self {
+            ReasonId::None => 0,
+            ReasonId::Moved => 1,
+            ReasonId::Subscription => 2,
+            ReasonId::Timeout => 3,
+            ReasonId::ChannelKick => 4,
+            ReasonId::ServerKick => 5,
+            ReasonId::Ban => 6,
+            ReasonId::ServerStopped => 7,
+            ReasonId::Disconnect => 8,
+            ReasonId::ChannelUpdated => 9,
+            ReasonId::ChannelEdited => 10,
+            ReasonId::ServerShutdown => 11,
+            ReasonId::Other(v) => v,
+        }
+    }
+
+    pub(crate) fn from_raw_field(
+        data: &mut HashMap<String, Option<String>>,
+        key: &'static str,
+    ) -> Result<Self> {
+        Ok(Self::from_raw(int_val_parser(data, key)?))
+    }
+}
+
+impl std::fmt::Display for ReasonId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReasonId::None => write!(f, "none"),
+            ReasonId::Moved => write!(f, "moved"),
+            ReasonId::Subscription => write!(f, "subscription"),
+            ReasonId::Timeout => write!(f, "timeout"),
+            ReasonId::ChannelKick => write!(f, "kicked from channel"),
+            ReasonId::ServerKick => write!(f, "kicked from server"),
+            ReasonId::Ban => write!(f, "banned"),
+            ReasonId::ServerStopped => write!(f, "server stopped"),
+            ReasonId::Disconnect => write!(f, "disconnected"),
+            ReasonId::ChannelUpdated => write!(f, "channel updated"),
+            ReasonId::ChannelEdited => write!(f, "channel edited"),
+            ReasonId::ServerShutdown => write!(f, "server shutdown"),
+            ReasonId::Other(v) => write!(f, "unknown reason {}", v),
+        }
+    }
+}
+
+/// Query API key returned from `apikeylist`. The key itself is only returned on creation.
+#[derive(Debug)]
+pub struct ApiKey {
+    pub id: usize,
+    pub sid: ServerId,
+    pub cldbid: ClientDBId,
+    pub scope: ApiScope,
+    /// Creation time as unix timestamp
+    pub created_at: i64,
+    /// Expiration as unix timestamp, `None` if unlimited
+    pub expires_at: Option<i64>,
+}
+
+impl ApiKey {
+    pub(crate) fn from_raw(mut data: HashMap<String, Option<String>>) -> Result<Self> {
+        let id = int_val_parser(&mut data, "id")?;
+        let sid = int_val_parser(&mut data, "sid")?;
+        let cldbid = int_val_parser(&mut data, "cldbid")?;
+        let scope = ApiScope::from_raw(&mut data)?;
+        let created_at = int_val_parser(&mut data, "created_at")?;
+        let expires_at = match int_val_parser(&mut data, "expires_at")? {
+            0 => None,
+            v => Some(v),
+        };
+
+        Ok(ApiKey {
+            id,
+            sid,
+            cldbid,
+            scope,
+            created_at,
+            expires_at,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Channel {
     /// Channel ID
     pub cid: ChannelId,
@@ -213,7 +1100,7 @@ impl Channel {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ChannelFull {
     /// Channel ID
     pub cid: ChannelId,
@@ -290,7 +1177,10 @@ impl ChannelFull {
 
 /// This struct defines the values of a channel that are changeable
 /// The difference to [ChannelFull] is that this does not contain values that are not changeable like
-#[derive(Debug, Default)]
+///
+/// Values that are `None` are left unchanged, an empty string clears the value.
+/// See [clear_topic](ChannelEdit::clear_topic) and [clear_password](ChannelEdit::clear_password).
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct ChannelEdit {
     /// The channel name
     ///
@@ -312,34 +1202,57 @@ pub struct ChannelEdit {
     pub channel_codec_quality: Option<u8>,
     pub channel_needed_talk_power: Option<i32>,
     pub channel_icon_id: Option<IconHash>,
+    pub channel_description: Option<String>,
+    pub channel_flag_maxclients_unlimited: Option<bool>,
+    pub channel_flag_maxfamilyclients_unlimited: Option<bool>,
+    /// Inherit the max family clients of the parent channel
+    pub channel_flag_maxfamilyclients_inherited: Option<bool>,
 }
 
 /// This defines when/if the channel gets automatically removed
-#[derive(Debug)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum ChannelLife {
     /// Permanent channel
     Permanent,
     /// Semi-Permanent channel (gets removed after server restart)
     SemiPermanent,
     /// Temporary channel (gets removed if empty)
+    #[default]
     Temporary,
 }
 
+impl ChannelLife {
+    /// Returns the channel flag setting this life
+    pub(crate) fn to_raw(self) -> &'static str {
+        match self {
+            ChannelLife::Permanent => "channel_flag_permanent=1",
+            ChannelLife::SemiPermanent => "channel_flag_semi_permanent=1",
+            ChannelLife::Temporary => "channel_flag_temporary=1",
+        }
+    }
+}
+
 impl ChannelEdit {
+    /// Clear the channel topic, emits an empty `channel_topic=`
+    pub fn clear_topic(mut self) -> Self {
+        self.channel_topic = Some(String::new());
+        self
+    }
+
+    /// Remove the channel password, emits an empty `channel_password=`
+    pub fn clear_password(mut self) -> Self {
+        self.channel_password = Some(String::new());
+        self
+    }
+
     pub(crate) fn to_raw(&self) -> String {
         let mut result = String::new();
 
         if let Some(x) = &self.channel_name {
             result += &format!(" channel_name={}", &escape_arg(x));
         }
-        if let Some(x) = &self.channel_life {
-            match x {
-                ChannelLife::Permanent => result += &format!(" channel_flag_permanent={}", 1),
-                ChannelLife::SemiPermanent => {
-                    result += &format!(" channel_flag_semi_permanent={}", 1)
-                }
-                ChannelLife::Temporary => result += &format!(" channel_flag_temporary={}", 1),
-            }
+        if let Some(x) = self.channel_life {
+            result += &format!(" {}", x.to_raw());
         }
         if let Some(x) = self.pid {
             result += &format!(" cpid={}", x);
@@ -374,6 +1287,18 @@ impl ChannelEdit {
         if let Some(x) = self.channel_icon_id {
             result += &format!(" channel_icon_id={}", x);
         }
+        if let Some(x) = &self.channel_description {
+            result += &format!(" channel_description={}", &escape_arg(x));
+        }
+        if let Some(x) = self.channel_flag_maxclients_unlimited {
+            result += &format!(" channel_flag_maxclients_unlimited={}", x as u8);
+        }
+        if let Some(x) = self.channel_flag_maxfamilyclients_unlimited {
+            result += &format!(" channel_flag_maxfamilyclients_unlimited={}", x as u8);
+        }
+        if let Some(x) = self.channel_flag_maxfamilyclients_inherited {
+            result += &format!(" channel_flag_maxfamilyclients_inherited={}", x as u8);
+        }
 
         result
     }
@@ -404,13 +1329,60 @@ impl From<&ChannelFull> for ChannelEdit {
             channel_codec_quality: c.channel_codec_quality.into(),
             channel_needed_talk_power: c.channel_needed_talk_power.into(),
             channel_icon_id: c.channel_icon_id.into(),
+            channel_description: None,
+            channel_flag_maxclients_unlimited: None,
+            channel_flag_maxfamilyclients_unlimited: None,
+            channel_flag_maxfamilyclients_inherited: None,
         }
     }
 }
 
-impl Default for ChannelLife {
-    fn default() -> Self {
-        Self::Temporary
+/// Changeable properties of the own client, used for `clientupdate`.
+/// Values that are `None` are not changed.
+#[derive(Debug, Default)]
+pub struct ClientUpdate {
+    pub client_nickname: Option<String>,
+    pub client_description: Option<String>,
+    pub client_away: Option<bool>,
+    /// Empty message to clear it
+    pub client_away_message: Option<String>,
+    pub client_is_channel_commander: Option<bool>,
+    pub client_input_muted: Option<bool>,
+    pub client_output_muted: Option<bool>,
+    /// Show the recording indicator
+    pub client_is_recording: Option<bool>,
+}
+
+impl ClientUpdate {
+    pub(crate) fn to_raw(&self) -> String {
+        let mut result = String::new();
+
+        if let Some(x) = &self.client_nickname {
+            result += &format!(" client_nickname={}", &escape_arg(x));
+        }
+        if let Some(x) = &self.client_description {
+            result += &format!(" client_description={}", &escape_arg(x));
+        }
+        if let Some(x) = self.client_away {
+            result += &format!(" client_away={}", x as u8);
+        }
+        if let Some(x) = &self.client_away_message {
+            result += &format!(" client_away_message={}", &escape_arg(x));
+        }
+        if let Some(x) = self.client_is_channel_commander {
+            result += &format!(" client_is_channel_commander={}", x as u8);
+        }
+        if let Some(x) = self.client_input_muted {
+            result += &format!(" client_input_muted={}", x as u8);
+        }
+        if let Some(x) = self.client_output_muted {
+            result += &format!(" client_output_muted={}", x as u8);
+        }
+        if let Some(x) = self.client_is_recording {
+            result += &format!(" client_is_recording={}", x as u8);
+        }
+
+        result
     }
 }
 
@@ -615,6 +1587,46 @@ impl ErrorResponse {
             _ => None,
         }
     }
+
+    /// Returns true if the client ID is invalid (512), for example on `clientfind` without matches
+    pub fn is_invalid_client_id(&self) -> bool {
+        self.id == 512
+    }
+
+    /// Returns true if the nickname is already in use (513)
+    pub fn is_name_taken(&self) -> bool {
+        self.id == 513
+    }
+
+    /// Returns true if the client already is in the target channel (770)
+    pub fn is_already_member(&self) -> bool {
+        self.id == 770
+    }
+
+    /// Returns true if the client is flooding (524), see [`anti_flood`](crate::QueryClient::anti_flood)
+    pub fn is_flooding(&self) -> bool {
+        self.id == 524
+    }
+
+    /// Returns true for a database empty result set (1281), returned by some list commands instead of an empty list
+    pub fn is_empty_result(&self) -> bool {
+        self.id == 1281
+    }
+
+    /// Returns true if an edit didn't change anything (1283)
+    pub fn is_no_modifications(&self) -> bool {
+        self.id == 1283
+    }
+
+    /// Returns true if the entry to create already exists (2561), for example a query login name
+    pub fn is_duplicate_entry(&self) -> bool {
+        self.id == 2561
+    }
+
+    /// Returns true if the client permissions are insufficient (2568)
+    pub fn is_permission_denied(&self) -> bool {
+        self.id == 2568
+    }
 }
 
 impl std::fmt::Display for ErrorResponse {
@@ -626,3 +1638,194 @@ impl std::fmt::Display for ErrorResponse {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::raw::parse_hashmap;
+
+    fn parse(line: &str) -> HashMap<String, Option<String>> {
+        parse_hashmap(vec![line.to_string()], false)
+    }
+
+    #[test]
+    fn test_clone_eq() {
+        let group = ServerGroup::from_raw(parse(
+            "sgid=6 name=Server\\sAdmin type=1 iconid=300 savedb=1",
+        ))
+        .unwrap();
+        assert_eq!(group, group.clone());
+
+        let client = OnlineClient::from_raw(parse(
+            "clid=5 cid=1 client_database_id=2 client_nickname=foo client_type=0",
+        ))
+        .unwrap();
+        assert_eq!(client, client.clone());
+
+        let channel = Channel::from_raw(parse(
+            "cid=2 pid=0 channel_order=1 channel_name=Lobby total_clients=1 channel_needed_subscribe_power=0",
+        ))
+        .unwrap();
+        assert_eq!(channel, channel.clone());
+
+        let channel_full = ChannelFull::from_raw(parse(
+            "cid=2 pid=0 channel_order=1 channel_name=Lobby total_clients=1 channel_needed_subscribe_power=0 channel_topic channel_flag_default=1 channel_flag_password=0 channel_flag_permanent=1 channel_flag_semi_permanent=0 channel_codec=4 channel_codec_quality=6 channel_needed_talk_power=0 channel_icon_id=0 seconds_empty=0 total_clients_family=1 channel_maxclients=-1 channel_maxfamilyclients=-1",
+        ))
+        .unwrap();
+        assert_eq!(channel_full, channel_full.clone());
+
+        let edit = ChannelEdit::from(&channel_full);
+        assert_eq!(edit, edit.clone());
+        assert_eq!(Some(ChannelLife::Permanent), edit.channel_life);
+        assert_ne!(edit, ChannelEdit::default());
+        assert_eq!(ChannelLife::Temporary, ChannelLife::default());
+    }
+
+    #[test]
+    fn test_error_predicates() {
+        let err = |id| ErrorResponse {
+            id,
+            msg: String::new(),
+        };
+        assert!(err(512).is_invalid_client_id());
+        assert!(err(513).is_name_taken());
+        assert!(err(1281).is_empty_result());
+        assert!(err(2568).is_permission_denied());
+        assert!(err(2561).is_duplicate_entry());
+        assert!(err(524).is_flooding());
+        assert!(err(1283).is_no_modifications());
+        assert!(err(770).is_already_member());
+        assert!(!err(768).is_already_member());
+        assert!(!err(1281).is_no_modifications());
+        assert!(!err(512).is_name_taken());
+        assert!(!err(2568).is_empty_result());
+        assert!(!err(0).is_name_taken());
+    }
+
+    #[test]
+    fn test_error_names() {
+        let err = |id| crate::ErrorResponse {
+            id,
+            msg: String::new(),
+        };
+        assert_eq!(Some("unknown error code"), err(0).error_name());
+        assert_eq!(Some("nickname is already in use"), err(513).error_name());
+        assert_eq!(Some("database empty result set"), err(1281).error_name());
+        assert_eq!(None, err(99999).error_name());
+        // Ts3Error::ServerError uses the same type
+        let e = crate::ServerError { response: err(513) }.build();
+        assert_eq!(
+            Some("nickname is already in use"),
+            e.error_response().and_then(|r| r.error_name())
+        );
+    }
+
+    #[test]
+    fn test_reason_ids() {
+        let known = [
+            (0, ReasonId::None),
+            (1, ReasonId::Moved),
+            (2, ReasonId::Subscription),
+            (3, ReasonId::Timeout),
+            (4, ReasonId::ChannelKick),
+            (5, ReasonId::ServerKick),
+            (6, ReasonId::Ban),
+            (7, ReasonId::ServerStopped),
+            (8, ReasonId::Disconnect),
+            (9, ReasonId::ChannelUpdated),
+            (10, ReasonId::ChannelEdited),
+            (11, ReasonId::ServerShutdown),
+        ];
+        for (raw, reason) in known.iter() {
+            assert_eq!(*reason, ReasonId::from_raw(*raw));
+            assert_eq!(*raw, reason.to_raw());
+        }
+        assert_eq!(ReasonId::Other(42), ReasonId::from_raw(42));
+        assert_eq!(42, ReasonId::Other(42).to_raw());
+        assert_eq!("kicked from server", ReasonId::ServerKick.to_string());
+        assert_eq!("unknown reason 42", ReasonId::from_raw(42).to_string());
+        let mut data = parse("reasonid=6");
+        assert_eq!(
+            ReasonId::Ban,
+            ReasonId::from_raw_field(&mut data, "reasonid").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_channel_edit_flags() {
+        let edit = ChannelEdit {
+            channel_name: Some("lobby".to_string()),
+            channel_description: Some("welcome | read the rules".to_string()),
+            channel_flag_maxclients_unlimited: Some(true),
+            channel_flag_maxfamilyclients_unlimited: Some(false),
+            channel_flag_maxfamilyclients_inherited: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(
+            " channel_name=lobby channel_description=welcome\\s\\p\\sread\\sthe\\srules channel_flag_maxclients_unlimited=1 channel_flag_maxfamilyclients_unlimited=0 channel_flag_maxfamilyclients_inherited=1",
+            edit.to_raw()
+        );
+        assert_eq!(
+            " channel_name=lobby",
+            ChannelEdit {
+                channel_name: Some("lobby".to_string()),
+                ..Default::default()
+            }
+            .to_raw()
+        );
+    }
+
+    #[test]
+    fn test_channel_edit_clear() {
+        let edit = ChannelEdit {
+            channel_topic: Some("old topic".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(" channel_topic=old\\stopic", edit.to_raw());
+        assert_eq!(
+            " channel_topic= channel_password=",
+            edit.clear_topic().clear_password().to_raw()
+        );
+        // unchanged
+        assert_eq!("", ChannelEdit::default().to_raw());
+    }
+
+    #[test]
+    fn test_online_client_full_optional() {
+        let line = "clid=5 cid=1 client_database_id=3 client_nickname=foo client_type=0 client_away=0 client_away_message client_flag_talking=0 client_input_muted=0 client_output_muted=0 client_input_hardware=1 client_output_hardware=1 client_talk_power=0 client_is_talker=0 client_is_priority_speaker=0 client_is_recording=0 client_is_channel_commander=0 client_unique_identifier=abc= client_servergroups=8 client_channel_group_id=8 client_channel_group_inherited_channel_id=1 client_version=3.5.6 client_platform=Linux client_idle_time=10 client_created=1 client_lastconnected=2 client_badges";
+        let client = OnlineClientFull::from_raw(parse(line)).unwrap();
+        assert_eq!(None, client.connection_client_ip);
+        assert_eq!(None, client.client_country);
+
+        let client = OnlineClientFull::from_raw(parse(&format!(
+            "{} client_country= connection_client_ip=127.0.0.1",
+            line
+        )))
+        .unwrap();
+        assert_eq!(Some("127.0.0.1"), client.connection_client_ip.as_deref());
+        assert_eq!(None, client.client_country);
+
+        let client =
+            OnlineClientFull::from_raw(parse(&format!("{} client_country=DE", line))).unwrap();
+        assert_eq!(Some("DE"), client.client_country.as_deref());
+    }
+
+    #[test]
+    fn test_online_client_full_no_groups() {
+        let line = "clid=5 cid=1 client_database_id=3 client_nickname=foo client_type=0 client_away=0 client_away_message client_flag_talking=0 client_input_muted=0 client_output_muted=0 client_input_hardware=1 client_output_hardware=1 client_talk_power=0 client_is_talker=0 client_is_priority_speaker=0 client_is_recording=0 client_is_channel_commander=0 client_unique_identifier=abc= client_channel_group_id=8 client_channel_group_inherited_channel_id=1 client_version=3.5.6 client_platform=Linux client_idle_time=10 client_created=1 client_lastconnected=2 client_badges";
+        for groups in &["client_servergroups=", "client_servergroups"] {
+            let client =
+                OnlineClientFull::from_raw(parse(&format!("{} {}", line, groups))).unwrap();
+            assert!(client.client_servergroups.is_empty());
+        }
+        let client =
+            OnlineClientFull::from_raw(parse(&format!("{} client_servergroups=6,8", line)))
+                .unwrap();
+        assert_eq!(
+            vec![crate::test::group_id(6), crate::test::group_id(8)],
+            client.client_servergroups
+        );
+        // missing key is still an error
+        assert!(OnlineClientFull::from_raw(parse(line)).is_err());
+    }
+}
@@ -0,0 +1,358 @@
+//! Parsed server notifications.
+//!
+//! Notifications are sent by the server for events registered via
+//! [`register_event`](crate::QueryClient::register_event).
+//! ```rust
+//! use ts3_query::event::Event;
+//!
+//! let event = Event::parse("notifyclientmoved ctid=3 reasonid=0 clid=5").unwrap();
+//! match event {
+//!     Event::ClientMoved { clid, ctid, .. } => {
+//!         assert_eq!("clid=5 ctid=3", format!("clid={} ctid={}", clid, ctid))
+//!     }
+//!     _ => unreachable!(),
+//! }
+//! ```
+use crate::raw::*;
+use crate::{ChannelId, ClientDBId, ClientId, InvalidResponse, ReasonId, Result};
+use std::collections::HashMap;
+
+/// Client that caused an event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invoker {
+    pub invokerid: ClientId,
+    pub invokername: String,
+    /// Not set for some server-side invokers
+    pub invokeruid: Option<String>,
+}
+
+impl Invoker {
+    /// Parse invoker fields, if present
+    fn from_raw(data: &mut HashMap<String, Option<String>>) -> Result<Option<Self>> {
+        if !data.contains_key("invokerid") {
+            return Ok(None);
+        }
+        let invokerid = int_val_parser(data, "invokerid")?;
+        let invokername = string_val_parser(data, "invokername")?;
+        let invokeruid = if data.contains_key("invokeruid") {
+            string_val_parser_opt(data, "invokeruid")?
+        } else {
+            None
+        };
+        Ok(Some(Invoker {
+            invokerid,
+            invokername,
+            invokeruid,
+        }))
+    }
+}
+
+/// Server notification. Field names are according to the query protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// `notifycliententerview`, client joined the server or became visible
+    ClientEnterView {
+        clid: ClientId,
+        /// Source channel, 0 when connecting
+        cfid: ChannelId,
+        /// Target channel
+        ctid: ChannelId,
+        reasonid: ReasonId,
+        client_nickname: String,
+        client_unique_identifier: String,
+        client_database_id: ClientDBId,
+        /// 0 For normal client, 1 for query
+        client_type: i8,
+        invoker: Option<Invoker>,
+    },
+    /// `notifyclientleftview`, client left the server or is no longer visible
+    ClientLeftView {
+        clid: ClientId,
+        /// Source channel
+        cfid: ChannelId,
+        /// Target channel, 0 when disconnecting
+        ctid: ChannelId,
+        reasonid: ReasonId,
+        reasonmsg: Option<String>,
+        invoker: Option<Invoker>,
+    },
+    /// `notifyclientmoved`, client switched channels
+    ClientMoved {
+        clid: ClientId,
+        /// Target channel
+        ctid: ChannelId,
+        reasonid: ReasonId,
+        invoker: Option<Invoker>,
+    },
+    /// `notifytextmessage`
+    TextMessage {
+        /// 1 for private, 2 for channel and 3 for server messages
+        targetmode: i32,
+        /// Recipient of private messages
+        target: Option<ClientId>,
+        msg: String,
+        invoker: Invoker,
+    },
+    /// `notifychannelcreated`
+    ChannelCreated {
+        cid: ChannelId,
+        /// Parent channel, 0 for top level channels
+        cpid: ChannelId,
+        channel_name: String,
+        invoker: Option<Invoker>,
+    },
+    /// `notifychanneledited`
+    ChannelEdited {
+        cid: ChannelId,
+        reasonid: ReasonId,
+        invoker: Option<Invoker>,
+        /// Changed channel properties, values are unescaped
+        changes: HashMap<String, Option<String>>,
+    },
+    /// `notifychanneldeleted`
+    ChannelDeleted {
+        cid: ChannelId,
+        invoker: Option<Invoker>,
+    },
+}
+
+impl Event {
+    /// Parse a notification line like `notifyclientmoved ctid=3 reasonid=0 clid=5`
+    ///
+    /// Returns [`InvalidResponse`](crate::Ts3Error::InvalidResponse) for unknown notifications.
+    pub fn parse(line: &str) -> Result<Event> {
+        let line = line.trim_end();
+        let mut split = line.splitn(2, ' ');
+        let name = split.next().unwrap_or_default();
+        let mut data = parse_hashmap(vec![split.next().unwrap_or_default().to_owned()], false);
+        let data = &mut data;
+
+        let event = match name {
+            "notifycliententerview" => Event::ClientEnterView {
+                clid: int_val_parser(data, "clid")?,
+                cfid: int_val_parser(data, "cfid")?,
+                ctid: int_val_parser(data, "ctid")?,
+                reasonid: ReasonId::from_raw_field(data, "reasonid")?,
+                client_nickname: string_val_parser(data, "client_nickname")?,
+                client_unique_identifier: string_val_parser(data, "client_unique_identifier")?,
+                client_database_id: int_val_parser(data, "client_database_id")?,
+                client_type: int_val_parser(data, "client_type")?,
+                invoker: Invoker::from_raw(data)?,
+            },
+            "notifyclientleftview" => Event::ClientLeftView {
+                clid: int_val_parser(data, "clid")?,
+                cfid: int_val_parser(data, "cfid")?,
+                ctid: int_val_parser(data, "ctid")?,
+                reasonid: ReasonId::from_raw_field(data, "reasonid")?,
+                reasonmsg: if data.contains_key("reasonmsg") {
+                    string_val_parser_opt(data, "reasonmsg")?
+                } else {
+                    None
+                },
+                invoker: Invoker::from_raw(data)?,
+            },
+            "notifyclientmoved" => Event::ClientMoved {
+                clid: int_val_parser(data, "clid")?,
+                ctid: int_val_parser(data, "ctid")?,
+                reasonid: ReasonId::from_raw_field(data, "reasonid")?,
+                invoker: Invoker::from_raw(data)?,
+            },
+            "notifytextmessage" => Event::TextMessage {
+                targetmode: int_val_parser(data, "targetmode")?,
+                target: if data.contains_key("target") {
+                    int_val_parser_opt(data, "target")?
+                } else {
+                    None
+                },
+                msg: string_val_parser(data, "msg")?,
+                invoker: Invoker::from_raw(data)?.ok_or_else(|| {
+                    InvalidResponse {
+                        context: "text message without invoker: ",
+                        data: line.to_owned(),
+                    }
+                    .build()
+                })?,
+            },
+            "notifychannelcreated" => Event::ChannelCreated {
+                cid: int_val_parser(data, "cid")?,
+                cpid: int_val_parser(data, "cpid")?,
+                channel_name: string_val_parser(data, "channel_name")?,
+                invoker: Invoker::from_raw(data)?,
+            },
+            "notifychanneledited" => Event::ChannelEdited {
+                cid: int_val_parser(data, "cid")?,
+                reasonid: ReasonId::from_raw_field(data, "reasonid")?,
+                invoker: Invoker::from_raw(data)?,
+                changes: data
+                    .drain()
+                    .map(|(k, v)| (k, v.map(unescape_val)))
+                    .collect(),
+            },
+            "notifychanneldeleted" => Event::ChannelDeleted {
+                cid: int_val_parser(data, "cid")?,
+                invoker: Invoker::from_raw(data)?,
+            },
+            _ => {
+                return InvalidResponse {
+                    context: "unknown event: ",
+                    data: line.to_owned(),
+                }
+                .fail()
+            }
+        };
+        Ok(event)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{channel_id, client_id, db_id};
+    use crate::Ts3Error;
+
+    fn invoker() -> Option<Invoker> {
+        Some(Invoker {
+            invokerid: client_id(1),
+            invokername: "foo bar".to_owned(),
+            invokeruid: Some("abc/d=".to_owned()),
+        })
+    }
+
+    #[test]
+    fn test_client_enter_view() {
+        let event = Event::parse("notifycliententerview cfid=0 ctid=3 reasonid=0 clid=5 client_unique_identifier=efg= client_nickname=a\\sb client_database_id=12 client_type=0 client_away=0\n\r").unwrap();
+        assert_eq!(
+            Event::ClientEnterView {
+                clid: client_id(5),
+                cfid: channel_id(0),
+                ctid: channel_id(3),
+                reasonid: ReasonId::None,
+                client_nickname: "a b".to_owned(),
+                client_unique_identifier: "efg=".to_owned(),
+                client_database_id: db_id(12),
+                client_type: 0,
+                invoker: None,
+            },
+            event
+        );
+    }
+
+    #[test]
+    fn test_client_left_view() {
+        let event = Event::parse("notifyclientleftview cfid=3 ctid=0 reasonid=5 invokerid=1 invokername=foo\\sbar invokeruid=abc\\/d= reasonmsg=bye\\sbye clid=5").unwrap();
+        assert_eq!(
+            Event::ClientLeftView {
+                clid: client_id(5),
+                cfid: channel_id(3),
+                ctid: channel_id(0),
+                reasonid: ReasonId::ServerKick,
+                reasonmsg: Some("bye bye".to_owned()),
+                invoker: invoker(),
+            },
+            event
+        );
+        let event = Event::parse("notifyclientleftview cfid=3 ctid=0 reasonid=8 clid=5").unwrap();
+        assert_eq!(
+            Event::ClientLeftView {
+                clid: client_id(5),
+                cfid: channel_id(3),
+                ctid: channel_id(0),
+                reasonid: ReasonId::Disconnect,
+                reasonmsg: None,
+                invoker: None,
+            },
+            event
+        );
+    }
+
+    #[test]
+    fn test_client_moved() {
+        let event = Event::parse("notifyclientmoved ctid=4 reasonid=1 invokerid=1 invokername=foo\\sbar invokeruid=abc\\/d= clid=5").unwrap();
+        assert_eq!(
+            Event::ClientMoved {
+                clid: client_id(5),
+                ctid: channel_id(4),
+                reasonid: ReasonId::Moved,
+                invoker: invoker(),
+            },
+            event
+        );
+    }
+
+    #[test]
+    fn test_text_message() {
+        let event = Event::parse("notifytextmessage targetmode=1 msg=hello\\sthere\\p target=7 invokerid=1 invokername=foo\\sbar invokeruid=abc\\/d=").unwrap();
+        assert_eq!(
+            Event::TextMessage {
+                targetmode: 1,
+                target: Some(client_id(7)),
+                msg: "hello there|".to_owned(),
+                invoker: invoker().unwrap(),
+            },
+            event
+        );
+        let err = Event::parse("notifytextmessage targetmode=3 msg=hi").unwrap_err();
+        assert!(matches!(err, Ts3Error::InvalidResponse { .. }));
+    }
+
+    #[test]
+    fn test_channel_created() {
+        let event = Event::parse("notifychannelcreated cid=9 cpid=2 channel_name=new\\schannel channel_order=0 invokerid=1 invokername=foo\\sbar invokeruid=abc\\/d=").unwrap();
+        assert_eq!(
+            Event::ChannelCreated {
+                cid: channel_id(9),
+                cpid: channel_id(2),
+                channel_name: "new channel".to_owned(),
+                invoker: invoker(),
+            },
+            event
+        );
+    }
+
+    #[test]
+    fn test_channel_edited() {
+        let event = Event::parse("notifychanneledited cid=9 reasonid=10 invokerid=1 invokername=foo\\sbar invokeruid=abc\\/d= channel_name=renamed\\schannel channel_topic").unwrap();
+        let changes: HashMap<String, Option<String>> = vec![
+            (
+                "channel_name".to_owned(),
+                Some("renamed channel".to_owned()),
+            ),
+            ("channel_topic".to_owned(), None),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            Event::ChannelEdited {
+                cid: channel_id(9),
+                reasonid: ReasonId::ChannelEdited,
+                invoker: invoker(),
+                changes,
+            },
+            event
+        );
+    }
+
+    #[test]
+    fn test_channel_deleted() {
+        let event =
+            Event::parse("notifychanneldeleted invokerid=0 invokername=Server cid=9").unwrap();
+        assert_eq!(
+            Event::ChannelDeleted {
+                cid: channel_id(9),
+                invoker: Some(Invoker {
+                    invokerid: client_id(0),
+                    invokername: "Server".to_owned(),
+                    invokeruid: None,
+                }),
+            },
+            event
+        );
+    }
+
+    #[test]
+    fn test_unknown_event() {
+        let err = Event::parse("notifyfoo a=b").unwrap_err();
+        assert!(matches!(err, Ts3Error::InvalidResponse { .. }));
+        assert!(Event::parse("").is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! Strictly typed IDs.
+//!
+//! Newtype wrappers for integer IDs, which are easy to mix up.
+//! For example `servergroupaddclient` expects client database IDs, not client IDs.
+//!
+//! With the `strict-ids` feature the ID aliases like [`ClientId`](crate::ClientId) resolve to these types,
+//! so [`QueryClient`](crate::QueryClient) methods only accept the matching ID.
+//! All wrappers format like their inner value and convert from/into it.
+//! ```rust
+//! use ts3_query::ids;
+//!
+//! let clid = ids::ClientId(5);
+//! let cid = ids::ChannelId(3);
+//! assert_eq!("clientmove clid=5 cid=3", format!("clientmove clid={} cid={}", clid, cid));
+//! let raw: u16 = clid.into();
+//! assert_eq!(5, raw);
+//! ```
+//! ```rust,compile_fail
+//! # use ts3_query::*;
+//! # fn add(client: &mut QueryClient) -> Result<(), Ts3Error> {
+//! // a client ID is not a client database ID
+//! client.server_group_add_client(ids::ServerGroupId(6), ids::ClientId(5))?;
+//! # Ok(()) }
+//! ```
+use std::fmt;
+use std::str::FromStr;
+
+macro_rules! id_newtype {
+    ($(#[$meta:meta])* $name:ident, $alias:ty) => {
+        $(#[$meta])*
+        #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name(pub $alias);
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Display::fmt(&self.0, f)
+            }
+        }
+
+        impl From<$alias> for $name {
+            fn from(id: $alias) -> Self {
+                Self(id)
+            }
+        }
+
+        impl From<$name> for $alias {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = std::num::ParseIntError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                s.parse().map(Self)
+            }
+        }
+    };
+}
+
+id_newtype!(
+    /// Strictly typed [`ClientId`](crate::ClientId)
+    ClientId,
+    u16
+);
+id_newtype!(
+    /// Strictly typed [`ChannelId`](crate::ChannelId)
+    ChannelId,
+    u64
+);
+id_newtype!(
+    /// Strictly typed [`ServerGroupID`](crate::ServerGroupID)
+    ServerGroupId,
+    u64
+);
+id_newtype!(
+    /// Strictly typed [`ClientDBId`](crate::ClientDBId)
+    ClientDbId,
+    u64
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::mock_client;
+
+    #[test]
+    fn test_format_identical() {
+        assert_eq!(
+            format!("clientkick clid={} reasonid=5", 7u16),
+            format!("clientkick clid={} reasonid=5", ClientId(7))
+        );
+        assert_eq!("3", ChannelId(3).to_string());
+        assert_eq!(Ok(ClientDbId(42)), "42".parse());
+        let cid: u64 = ChannelId::from(3).into();
+        assert_eq!(3, cid);
+    }
+
+    #[test]
+    fn test_command_identical() {
+        let (mut client, handle) = mock_client(&[]);
+        client
+            .server_group_add_client(ServerGroupId(6), ClientDbId(42))
+            .unwrap();
+        client
+            .raw_command(format!(
+                "servergroupaddclient sgid={} cldbid={}",
+                6u64, 42u64
+            ))
+            .unwrap();
+        client.move_client(ClientId(5), ChannelId(3), None).unwrap();
+        client
+            .raw_command(format!("clientmove clid={} cid={}", 5u16, 3u64))
+            .unwrap();
+        drop(client);
+        let commands = handle.join().unwrap();
+        assert_eq!("servergroupaddclient sgid=6 cldbid=42", commands[0]);
+        assert_eq!(commands[0], commands[1]);
+        assert_eq!("clientmove clid=5 cid=3", commands[2]);
+        assert_eq!(commands[2], commands[3]);
+    }
+}
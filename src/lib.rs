@@ -3,6 +3,9 @@
 //!
 //! A connectivity checking wrapper is available under [managed](managed) when enabling its feature.
 //!
+//! The `tracing` feature emits a [tracing](https://docs.rs/tracing) span per command, with passwords redacted
+//! and long commands truncated, and events for the response line count and errors.
+//!
 //! # Examples
 //! Simple auth + clients of a server group
 //! ```rust,no_run
@@ -14,7 +17,7 @@
 //! client.login("serveradmin", "password")?;
 //! client.select_server_by_port(9987)?;
 //!
-//! let group_clients = client.servergroup_client_cldbids(7)?;
+//! let group_clients = client.get_servergroup_client_list(7u64.into())?;
 //! println!("Got clients in group 7: {:?}",group_clients);
 //!
 //! client.logout()?;
@@ -94,14 +97,19 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
 use snafu::{Backtrace, OptionExt, ResultExt, Snafu};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fmt::{Debug, Write as FmtWrite};
 use std::io::{self, BufRead, BufReader, Write};
-use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::net::{Shutdown, SocketAddr, TcpStream, ToSocketAddrs};
+use std::str::FromStr;
 use std::string::FromUtf8Error;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 mod data;
+pub mod event;
+#[cfg_attr(docsrs, doc(cfg(feature = "strict-ids")))]
+#[cfg(feature = "strict-ids")]
+pub mod ids;
 #[cfg_attr(docsrs, doc(cfg(feature = "managed")))]
 #[cfg(feature = "managed")]
 pub mod managed;
@@ -113,6 +121,7 @@ use raw::*;
 use std::fmt;
 
 /// Target for message sending
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MessageTarget {
     /// Send to client
     Client(ClientId),
@@ -132,6 +141,66 @@ impl fmt::Display for MessageTarget {
     }
 }
 
+/// Group a privilege key grants
+pub enum PrivilegeKeyTarget {
+    /// Server group
+    ServerGroup(ServerGroupID),
+    /// Channel group in the channel
+    ChannelGroup(ChannelGroupId, ChannelId),
+}
+
+impl fmt::Display for PrivilegeKeyTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::ServerGroup(id) => write!(f, "tokentype=0 tokenid1={} tokenid2=0", id),
+            Self::ChannelGroup(id, cid) => {
+                write!(f, "tokentype=1 tokenid1={} tokenid2={}", id, cid)
+            }
+        }
+    }
+}
+
+/// Event type to register notifications for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyEvent {
+    /// Server events like clients joining or leaving
+    Server,
+    /// Events of the channel, 0 for all channels
+    Channel(ChannelId),
+    /// Text messages sent to the server
+    TextServer,
+    /// Text messages sent to the current channel of this client
+    TextChannel,
+    /// Private text messages sent to this client
+    TextPrivate,
+    /// Privilege key usage
+    TokenUsed,
+}
+
+impl fmt::Display for NotifyEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Server => write!(f, "event=server"),
+            Self::Channel(id) => write!(f, "event=channel id={}", id),
+            Self::TextServer => write!(f, "event=textserver"),
+            Self::TextChannel => write!(f, "event=textchannel"),
+            Self::TextPrivate => write!(f, "event=textprivate"),
+            Self::TokenUsed => write!(f, "event=tokenused"),
+        }
+    }
+}
+
+/// DoS limit that triggered a [`ResponseLimit`](Ts3Error::ResponseLimit) error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitKind {
+    /// Lines per response, see [`limit_lines`](QueryClient::limit_lines)
+    Lines,
+    /// Bytes per line, see [`limit_line_bytes`](QueryClient::limit_line_bytes)
+    LineBytes,
+    /// Bytes per response, see [`limit_total_bytes`](QueryClient::limit_total_bytes)
+    TotalBytes,
+}
+
 #[derive(Snafu, Debug)]
 pub enum Ts3Error {
     /// Error on response conversion with invalid utf8 data
@@ -146,9 +215,21 @@ pub enum Ts3Error {
         context: &'static str,
         source: io::Error,
     },
-    /// Reached EOF reading response, server closed connection / timeout.
+    /// Reached EOF reading response, server closed connection.
     #[snafu(display("IO Error: Connection closed"))]
     ConnectionClosed { backtrace: Backtrace },
+    /// Read timeout reached while waiting for a response.
+    #[snafu(display("IO Error: Timeout {}{}", context, source))]
+    Timeout {
+        /// Context of action, empty per default.
+        ///
+        /// Please use a format like `"reading connection: "`
+        context: &'static str,
+        source: io::Error,
+    },
+    /// Server didn't send the `TS3` greeting, not a query port.
+    #[snafu(display("Not a query port, expected TS3 greeting, got: {:?}", got))]
+    NotAQueryPort { got: String, backtrace: Backtrace },
     #[snafu(display("No valid socket address provided."))]
     InvalidSocketAddress { backtrace: Backtrace },
     /// Invalid response error. Server returned unexpected data.
@@ -166,6 +247,12 @@ pub enum Ts3Error {
         source: std::num::ParseIntError,
         backtrace: Backtrace,
     },
+    #[snafu(display("Got invalid float response {}: {}", data, source))]
+    InvalidFloatResponse {
+        data: String,
+        source: std::num::ParseFloatError,
+        backtrace: Backtrace,
+    },
     /// TS3-Server error response
     #[snafu(display("Server responded with error: {}", response))]
     ServerError {
@@ -175,8 +262,9 @@ pub enum Ts3Error {
     /// Maximum amount of response bytes/lines reached, DDOS limit prevented further data read.
     ///
     /// This will probably cause the current connection to become invalid due to remaining data in the connection.
-    #[snafu(display("Invalid response, DDOS limit reached: {:?}", response))]
+    #[snafu(display("Invalid response, DDOS limit {:?} reached: {:?}", kind, response))]
     ResponseLimit {
+        kind: LimitKind,
         response: Vec<String>,
         backtrace: Backtrace,
     },
@@ -201,10 +289,38 @@ pub enum Ts3Error {
 impl Ts3Error {
     /// Returns true if the error is of kind ServerError
     pub fn is_error_response(&self) -> bool {
-        match self {
-            Ts3Error::ServerError { .. } => true,
-            _ => false,
-        }
+        matches!(self, Ts3Error::ServerError { .. })
+    }
+    /// Returns true if the error is caused by the connection (IO error, timeout or connection closed).
+    /// Such errors may be resolved by reconnecting.
+    pub fn is_connection_error(&self) -> bool {
+        matches!(
+            self,
+            Ts3Error::Io { .. } | Ts3Error::ConnectionClosed { .. } | Ts3Error::Timeout { .. }
+        )
+    }
+    /// Returns true if the error is a read timeout, the server didn't respond in time.
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, Ts3Error::Timeout { .. })
+    }
+    /// Returns true if the error is a server error for an empty result set.
+    ///
+    /// Returned by some list commands instead of an empty list.
+    pub fn is_empty_result(&self) -> bool {
+        self.error_response().is_some_and(|r| r.is_empty_result())
+    }
+    /// Returns true if the error is a server error for an edit without changes.
+    ///
+    /// Ignored by [`edit_channel`](QueryClient::edit_channel) and [`server_edit`](QueryClient::server_edit).
+    pub fn is_no_modifications(&self) -> bool {
+        self.error_response()
+            .is_some_and(|r| r.is_no_modifications())
+    }
+    /// Returns true if the error is a server error for moving a client into its current channel.
+    ///
+    /// Can be ignored when repositioning clients, see [`move_client`](QueryClient::move_client).
+    pub fn is_already_member(&self) -> bool {
+        self.error_response().is_some_and(|r| r.is_already_member())
     }
     /// Returns the [`ErrorResponse`](ErrorResponse) if existing.
     pub fn error_response(&self) -> Option<&ErrorResponse> {
@@ -213,6 +329,15 @@ impl Ts3Error {
             _ => None,
         }
     }
+    /// Map io errors to [`Timeout`](Ts3Error::Timeout) for timeouts, [`Io`](Ts3Error::Io) otherwise
+    fn from_io(context: &'static str) -> impl FnOnce(io::Error) -> Ts3Error {
+        move |source| match source.kind() {
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => {
+                Ts3Error::Timeout { context, source }
+            }
+            _ => Ts3Error::Io { context, source },
+        }
+    }
 }
 
 impl From<io::Error> for Ts3Error {
@@ -224,43 +349,330 @@ impl From<io::Error> for Ts3Error {
     }
 }
 
+#[cfg(not(feature = "mock"))]
+type Reader = BufReader<TcpStream>;
+#[cfg(not(feature = "mock"))]
+type Writer = TcpStream;
+/// Boxed transports, allowing injected streams via [`from_streams`](QueryClient::from_streams)
+#[cfg(feature = "mock")]
+type Reader = BufReader<Box<dyn Read + Send + Sync>>;
+#[cfg(feature = "mock")]
+type Writer = Box<dyn Write + Send + Sync>;
+
+/// Split a connection into reader and writer
+#[cfg(not(feature = "mock"))]
+fn split_transport(stream: &TcpStream) -> io::Result<(Reader, Writer)> {
+    Ok((BufReader::new(stream.try_clone()?), stream.try_clone()?))
+}
+
+/// Split a connection into reader and writer
+#[cfg(feature = "mock")]
+fn split_transport(stream: &TcpStream) -> io::Result<(Reader, Writer)> {
+    Ok((
+        BufReader::new(Box::new(stream.try_clone()?)),
+        Box::new(stream.try_clone()?),
+    ))
+}
+
+/// Command writer, spaces commands by the [`anti_flood`](QueryClient::anti_flood) interval
+struct CommandWriter {
+    inner: Writer,
+    /// Minimum interval between commands
+    interval: Option<Duration>,
+    /// Start of the last command
+    last: Option<Instant>,
+    /// Next write starts a new command
+    new_command: bool,
+    /// Last written command, for flood retries
+    command: Vec<u8>,
+    sleep: fn(Duration),
+    /// Span of the last command, covering its write and response
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl CommandWriter {
+    fn new(inner: Writer) -> Self {
+        Self {
+            inner,
+            interval: None,
+            last: None,
+            new_command: true,
+            command: Vec::new(),
+            sleep: std::thread::sleep,
+            #[cfg(feature = "tracing")]
+            span: tracing::Span::none(),
+        }
+    }
+
+    /// Replace the connection, keeping the anti flood state
+    fn replace(&mut self, inner: Writer) {
+        self.inner = inner;
+        self.new_command = true;
+        self.command.clear();
+    }
+
+    /// Wait until the interval since the last command passed
+    fn throttle(&mut self) {
+        if let (Some(interval), Some(last)) = (self.interval, self.last) {
+            let elapsed = last.elapsed();
+            if elapsed < interval {
+                (self.sleep)(interval - elapsed);
+            }
+        }
+        self.last = Some(Instant::now());
+    }
+}
+
+impl Write for CommandWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.new_command {
+            self.throttle();
+            self.command.clear();
+            self.new_command = false;
+            #[cfg(feature = "tracing")]
+            {
+                self.span = tracing::debug_span!("command", command = tracing::field::Empty);
+            }
+        }
+        #[cfg(feature = "tracing")]
+        let _span = self.span.enter();
+        let n = self.inner.write(buf)?;
+        self.command.extend_from_slice(&buf[..n]);
+        if buf[..n].contains(&b'\n') {
+            self.new_command = true;
+            #[cfg(feature = "tracing")]
+            self.span.record(
+                "command",
+                tracing::field::display(redact_command(&String::from_utf8_lossy(&self.command))),
+            );
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
 /// Ts3 Query client with active connection
 pub struct QueryClient {
-    rx: BufReader<TcpStream>,
-    tx: TcpStream,
+    rx: Reader,
+    tx: CommandWriter,
+    /// TCP connection, none for injected streams
+    stream: Option<TcpStream>,
+    /// Resolved address connected to, none for injected streams
+    addr: Option<SocketAddr>,
+    conn_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    greeting_timeout: Option<Duration>,
     limit_lines: usize,
     limit_lines_bytes: u64,
+    limit_total_bytes: u64,
+    /// Use legacy `token*` commands instead of `privilegekey*`
+    legacy_tokens: bool,
+    /// Replace invalid UTF-8 in responses instead of failing
+    lossy_utf8: bool,
+    /// Last used client file transfer ID
+    ft_id: u16,
+    /// Connection was closed via [`close`](QueryClient::close)
+    closed: bool,
+    /// Login (user, password) to replay on reconnect
+    login: Option<(String, String)>,
+    /// Server selection to replay on reconnect
+    selected: Option<ServerSelection>,
+    /// Cached client ID of this connection, changes with the selected server
+    clid: Option<ClientId>,
+    /// Cached ID of the selected server
+    sid: Option<ServerId>,
+    /// Cached client UIDs, most recently used first
+    uids: VecDeque<(ClientId, String)>,
+    /// Notifications received while reading responses, see [`poll_event`](QueryClient::poll_event)
+    events: VecDeque<String>,
+    /// Observer of read lines, see [`on_line`](QueryClient::on_line)
+    line_observer: Option<LineObserver>,
+}
+
+/// Observer of read response lines
+type LineObserver = Box<dyn FnMut(&str) + Send + Sync>;
+
+// QueryClient has to stay Send + Sync, checked at compile time
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<QueryClient>();
+};
+
+impl Debug for QueryClient {
+    /// Prints the peer address and configuration, without streams or login credentials.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QueryClient")
+            .field("addr", &self.addr)
+            .field("conn_timeout", &self.conn_timeout)
+            .field("timeout", &self.timeout)
+            .field("limit_lines", &self.limit_lines)
+            .field("limit_lines_bytes", &self.limit_lines_bytes)
+            .field("limit_total_bytes", &self.limit_total_bytes)
+            .field("legacy_tokens", &self.legacy_tokens)
+            .field("lossy_utf8", &self.lossy_utf8)
+            .field("closed", &self.closed)
+            .field("selected", &self.selected)
+            .field("clid", &self.clid)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Redact passwords and tokens of a command for logging, truncated to [`LIMIT_TRACE_COMMAND`] bytes
+#[cfg(feature = "tracing")]
+fn redact_command(command: &str) -> String {
+    let command = command.trim_end();
+    if let Some(args) = command.strip_prefix("login ").filter(|v| !v.contains('=')) {
+        // login <user> <password>
+        return format!(
+            "login {} <redacted>",
+            args.split(' ').next().unwrap_or_default()
+        );
+    }
+    let mut res = command
+        .split(' ')
+        .map(|arg| match arg.split_once('=') {
+            Some((key, _)) if key.contains("password") || key == "cpw" || key == "token" => {
+                format!("{}=<redacted>", key)
+            }
+            _ => arg.to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    if res.len() > LIMIT_TRACE_COMMAND {
+        let mut end = LIMIT_TRACE_COMMAND;
+        while !res.is_char_boundary(end) {
+            end -= 1;
+        }
+        res.truncate(end);
+        res.push_str("...");
+    }
+    res
+}
+
+/// Virtual server selection of a [`QueryClient`]
+#[derive(Debug, Clone, Copy)]
+enum ServerSelection {
+    Port(u16),
+    Id(ServerId),
+}
+
+/// Streaming response iterator, see [`QueryClient::raw_command_iter`]
+struct ResponseIter<'a> {
+    client: &'a mut QueryClient,
+    /// Lines read so far, for DoS limit
+    lines: usize,
+    /// Bytes read so far, for DoS limit
+    bytes: u64,
+    /// Line buffer for read_line
+    buffer: Vec<String>,
+    /// Error that occurred before reading
+    pending: Option<Ts3Error>,
+    done: bool,
+}
+
+impl Iterator for ResponseIter<'_> {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if let Some(e) = self.pending.take() {
+            self.done = true;
+            return Some(Err(e));
+        }
+        while self.lines < self.client.limit_lines {
+            self.lines += 1;
+            let limit = self.client.limit_lines_bytes;
+            match self
+                .client
+                .read_line(limit, &mut self.bytes, &mut self.buffer)
+            {
+                Ok(true) => {
+                    // empty lines are skipped
+                    if let Some(line) = self.buffer.pop() {
+                        return Some(Ok(line));
+                    }
+                }
+                Ok(false) => {
+                    self.done = true;
+                    return None;
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        self.done = true;
+        Some(
+            ResponseLimit {
+                kind: LimitKind::Lines,
+                response: Vec::new(),
+            }
+            .fail(),
+        )
+    }
+}
+
+impl Drop for ResponseIter<'_> {
+    fn drop(&mut self) {
+        // consume remaining response, otherwise it would be read by the next command
+        for _ in self {}
+    }
 }
 
 /// Default DoS limit for read lines
 pub const LIMIT_READ_LINES: usize = 100;
 /// Default DoS limit for read bytes per line
 pub const LIMIT_LINE_BYTES: u64 = 64_000;
+/// Default DoS limit for read bytes per response
+pub const LIMIT_TOTAL_BYTES: u64 = 16_000_000;
+
+/// Wait before retrying a command rejected for flooding, see [`QueryClient::anti_flood`]
+pub const FLOOD_RETRY_WAIT: Duration = Duration::from_secs(1);
+
+/// Minimum bytes per line limit applied while reading snapshots
+pub const LIMIT_SNAPSHOT_BYTES: u64 = 64_000_000;
+
+const ERR_NO_FILES: usize = 2055;
+/// Returned by `channelfind` if no channel matches
+const ERR_INVALID_CHANNEL_ID: usize = 768;
+/// Client ID key of `whoami`, differs from other responses
+const KEY_CLIENT_ID_SELF: &str = "client_id";
+/// Maximum amount of queued notifications, older ones are dropped
+pub const LIMIT_EVENT_QUEUE: usize = 1000;
+/// Amount of cached client UIDs, see [`QueryClient::client_uid_from_clid`]
+const UID_CACHE_SIZE: usize = 64;
+/// Maximum bytes of a command logged by the `tracing` feature
+#[cfg(feature = "tracing")]
+const LIMIT_TRACE_COMMAND: usize = 256;
 
 type Result<T> = ::std::result::Result<T, Ts3Error>;
 
 impl Drop for QueryClient {
+    /// Best-effort quit, prefer [`close`](QueryClient::close) to handle errors.
     fn drop(&mut self) {
+        if self.closed {
+            return;
+        }
         #[allow(unused_variables)]
         if let Err(e) = self.quit() {
             #[cfg(feature = "debug_response")]
             eprintln!("Can't quit on drop: {}", e);
         }
-        let _ = self.tx.shutdown(Shutdown::Both);
+        let _ = self.shutdown();
     }
 }
 
 impl QueryClient {
     /// Create new query connection
     pub fn new<A: ToSocketAddrs>(addr: A) -> Result<Self> {
-        let (rx, tx) = Self::new_inner(addr, None, None)?;
-
-        Ok(Self {
-            rx,
-            tx,
-            limit_lines: LIMIT_READ_LINES,
-            limit_lines_bytes: LIMIT_LINE_BYTES,
-        })
+        Self::with_timeout(addr, None, None)
     }
 
     /// Create new query connection with timeouts
@@ -271,16 +683,191 @@ impl QueryClient {
         t_connect: Option<Duration>,
         timeout: Option<Duration>,
     ) -> Result<Self> {
-        let (rx, tx) = Self::new_inner(addr, timeout, t_connect)?;
+        Self::with_greeting_timeout(addr, t_connect, timeout, None)
+    }
+
+    /// Create new query connection with timeouts and a separate timeout for reading the greeting
+    ///
+    /// `t_connect` is used for connection, `t_greeting` for the server greeting and `timeout` for read/write operations.
+    /// If `t_greeting` is none, `timeout` is used for the greeting.
+    pub fn with_greeting_timeout<A: ToSocketAddrs>(
+        addr: A,
+        t_connect: Option<Duration>,
+        timeout: Option<Duration>,
+        t_greeting: Option<Duration>,
+    ) -> Result<Self> {
+        let addr = addr
+            .to_socket_addrs()
+            .context(Io {
+                context: "invalid socket address",
+            })?
+            .next()
+            .context(InvalidSocketAddress {})?;
+        let (rx, tx, stream) = Self::new_inner(&addr, timeout, t_connect, t_greeting)?;
 
         Ok(Self {
             rx,
-            tx,
+            tx: CommandWriter::new(tx),
+            stream: Some(stream),
+            addr: Some(addr),
+            conn_timeout: t_connect,
+            timeout,
+            greeting_timeout: t_greeting,
             limit_lines: LIMIT_READ_LINES,
             limit_lines_bytes: LIMIT_LINE_BYTES,
+            limit_total_bytes: LIMIT_TOTAL_BYTES,
+            legacy_tokens: false,
+            lossy_utf8: false,
+            ft_id: 0,
+            closed: false,
+            login: None,
+            selected: None,
+            clid: None,
+            sid: None,
+            uids: VecDeque::new(),
+            events: VecDeque::new(),
+            line_observer: None,
+        })
+    }
+
+    /// Open a second, independent connection to the same server with the same timeouts, limits, anti flood and token mode.
+    ///
+    /// The new connection is not logged in and has no server selected.
+    pub fn reconnect_like(&self) -> Result<QueryClient> {
+        let addr = self.addr.context(InvalidSocketAddress {})?;
+        let (rx, tx, stream) = Self::new_inner(
+            &addr,
+            self.timeout,
+            self.conn_timeout,
+            self.greeting_timeout,
+        )?;
+
+        let mut tx = CommandWriter::new(tx);
+        tx.interval = self.tx.interval;
+
+        Ok(Self {
+            rx,
+            tx,
+            stream: Some(stream),
+            addr: Some(addr),
+            conn_timeout: self.conn_timeout,
+            timeout: self.timeout,
+            greeting_timeout: self.greeting_timeout,
+            limit_lines: self.limit_lines,
+            limit_lines_bytes: self.limit_lines_bytes,
+            limit_total_bytes: self.limit_total_bytes,
+            legacy_tokens: self.legacy_tokens,
+            lossy_utf8: self.lossy_utf8,
+            ft_id: 0,
+            closed: false,
+            login: None,
+            selected: None,
+            clid: None,
+            sid: None,
+            uids: VecDeque::new(),
+            events: VecDeque::new(),
+            line_observer: None,
         })
     }
 
+    /// Reopen the connection, replaying the last successful login and server selection.
+    ///
+    /// Can be used to recover from a broken connection. The old connection is shut down without sending `quit`.
+    pub fn reconnect(&mut self) -> Result<()> {
+        let addr = self.addr.context(InvalidSocketAddress {})?;
+        let _ = self.shutdown();
+        let (rx, tx, stream) = Self::new_inner(
+            &addr,
+            self.timeout,
+            self.conn_timeout,
+            self.greeting_timeout,
+        )?;
+        self.rx = rx;
+        self.tx.replace(tx);
+        self.stream = Some(stream);
+        self.closed = false;
+        self.clid = None;
+        self.sid = None;
+        self.uids.clear();
+
+        if let Some((user, password)) = self.login.clone() {
+            self.login(user, password)?;
+        }
+        match self.selected {
+            Some(ServerSelection::Port(port)) => self.select_server_by_port(port)?,
+            Some(ServerSelection::Id(sid)) => self.select_server_by_id(sid)?,
+            None => (),
+        }
+        Ok(())
+    }
+
+    /// Run `f` up to `attempts` times, [`reconnect`](QueryClient::reconnect)ing between attempts.
+    ///
+    /// Only [connection errors](Ts3Error::is_connection_error) are retried,
+    /// server errors are returned immediately as retrying wouldn't change the outcome.
+    pub fn with_retry<T, F>(&mut self, attempts: usize, mut f: F) -> Result<T>
+    where
+        F: FnMut(&mut Self) -> Result<T>,
+    {
+        let mut res = f(self);
+        for _ in 1..attempts {
+            match &res {
+                Err(e) if e.is_connection_error() => (),
+                _ => return res,
+            }
+            res = self.reconnect().and_then(|_| f(self));
+        }
+        res
+    }
+
+    /// Returns the address this client was created for, kept across [`reconnect`](QueryClient::reconnect)s.
+    ///
+    /// None for clients created from injected streams.
+    pub fn addr(&self) -> Option<SocketAddr> {
+        self.addr
+    }
+
+    /// Returns the remote address of the current connection
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.tcp_stream()?.peer_addr()
+    }
+
+    /// Returns the local address of the current connection
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.tcp_stream()?.local_addr()
+    }
+
+    /// Returns the TCP connection, errors for injected streams
+    fn tcp_stream(&self) -> io::Result<&TcpStream> {
+        self.stream
+            .as_ref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "no tcp connection"))
+    }
+
+    /// Shutdown the TCP connection, if existing
+    fn shutdown(&self) -> io::Result<()> {
+        match &self.stream {
+            Some(stream) => stream.shutdown(Shutdown::Both),
+            None => Ok(()),
+        }
+    }
+
+    /// Set read and write timeouts of the current connection, `None` for no timeout.
+    ///
+    /// A [`reconnect`](QueryClient::reconnect) uses the timeout of this client's creation again.
+    /// Does nothing for clients created from injected streams.
+    pub fn set_timeout(&mut self, read: Option<Duration>, write: Option<Duration>) -> Result<()> {
+        if let Some(stream) = &self.stream {
+            stream.set_read_timeout(read).context(Io {
+                context: "setting read timeout: ",
+            })?;
+            stream.set_write_timeout(write).context(Io {
+                context: "setting write timeout: ",
+            })?;
+        }
+        Ok(())
+    }
+
     /// Set new maximum amount of lines to read per response, until DoS protection triggers.
     pub fn limit_lines(&mut self, limit: usize) {
         self.limit_lines = limit;
@@ -292,23 +879,137 @@ impl QueryClient {
         self.limit_lines_bytes = limit;
     }
 
+    /// Set new maximum amount of bytes per response to read until DoS protection triggers.
+    pub fn limit_total_bytes(&mut self, limit: u64) {
+        self.limit_total_bytes = limit;
+    }
+
+    /// Space commands by at least `interval` to stay below the flood limit of the server, `None` to disable.
+    ///
+    /// If set, commands rejected for [flooding](ErrorResponse::is_flooding) are retried once after waiting
+    /// for [`FLOOD_RETRY_WAIT`](FLOOD_RETRY_WAIT).
+    /// Query clients on the server whitelist are not subject to the flood limit.
+    pub fn anti_flood(&mut self, interval: Option<Duration>) {
+        self.tx.interval = interval;
+    }
+
+    /// Set an observer receiving every line read from the server, without line endings.
+    ///
+    /// Includes error lines and notifications. Useful for logging responses to a custom sink.
+    /// Kept on [`reconnect`](QueryClient::reconnect), not copied by [`reconnect_like`](QueryClient::reconnect_like).
+    pub fn on_line<F>(&mut self, observer: F)
+    where
+        F: FnMut(&str) + Send + Sync + 'static,
+    {
+        self.line_observer = Some(Box::new(observer));
+    }
+
+    /// Remove the observer set via [`on_line`](QueryClient::on_line)
+    pub fn clear_on_line(&mut self) {
+        self.line_observer = None;
+    }
+
+    /// Replace invalid UTF-8 in responses with `U+FFFD` instead of failing with [`Utf8Error`](Ts3Error::Utf8Error).
+    ///
+    /// Disabled by default.
+    pub fn lossy_utf8(&mut self, lossy: bool) {
+        self.lossy_utf8 = lossy;
+    }
+
+    /// Use the legacy `token*` commands instead of `privilegekey*` for privilege keys.
+    ///
+    /// Required for old servers not supporting `privilegekey*`.
+    pub fn legacy_tokens(&mut self, legacy: bool) {
+        self.legacy_tokens = legacy;
+    }
+
+    /// Run `f` with a bytes per line limit of `limit`, restoring the previous limit afterwards, even on error.
+    ///
+    /// The bytes per response limit is raised to at least `limit` meanwhile.
+    pub fn with_line_byte_limit<T, F>(&mut self, limit: u64, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        let previous = (self.limit_lines_bytes, self.limit_total_bytes);
+        self.limit_lines_bytes = limit;
+        self.limit_total_bytes = self.limit_total_bytes.max(limit);
+        let res = f(self);
+        (self.limit_lines_bytes, self.limit_total_bytes) = previous;
+        res
+    }
+
+    /// Update properties of this client
+    ///
+    /// Performs `clientupdate`
+    pub fn client_update(&mut self, update: &ClientUpdate) -> Result<()> {
+        writeln!(&mut self.tx, "clientupdate{}", update.to_raw())?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
     /// Rename this client, performs `clientupdate client_nickname` escaping the name
     pub fn rename<T: AsRef<str>>(&mut self, name: T) -> Result<()> {
+        self.client_update(&ClientUpdate {
+            client_nickname: Some(name.as_ref().to_owned()),
+            ..Default::default()
+        })
+    }
+
+    /// Set channel commander status of this client
+    ///
+    /// Performs `clientupdate client_is_channel_commander`
+    pub fn set_channel_commander(&mut self, on: bool) -> Result<()> {
+        self.client_update(&ClientUpdate {
+            client_is_channel_commander: Some(on),
+            ..Default::default()
+        })
+    }
+
+    /// Show or hide the recording indicator of this client
+    ///
+    /// Performs `clientupdate client_is_recording`
+    pub fn set_recording_indicator(&mut self, on: bool) -> Result<()> {
+        self.client_update(&ClientUpdate {
+            client_is_recording: Some(on),
+            ..Default::default()
+        })
+    }
+
+    /// Set away status of this client with optional message. Clears the away message when not away.
+    ///
+    /// Performs `clientupdate client_away client_away_message`
+    pub fn set_away(&mut self, away: bool, message: Option<&str>) -> Result<()> {
+        let message = if away {
+            message.unwrap_or_default()
+        } else {
+            ""
+        };
+        self.client_update(&ClientUpdate {
+            client_away: Some(away),
+            client_away_message: Some(message.to_owned()),
+            ..Default::default()
+        })
+    }
+
+    /// Update channel name, performs `channeledit channel_name`
+    pub fn rename_channel<T: AsRef<str>>(&mut self, channel: ChannelId, name: T) -> Result<()> {
         writeln!(
             &mut self.tx,
-            "clientupdate client_nickname={}",
+            "channeledit cid={} channel_name={}",
+            channel,
             escape_arg(name)
         )?;
         let _ = self.read_response()?;
         Ok(())
     }
 
-    /// Update channel name, performs `channeledit channel_name`
-    pub fn rename_channel<T: AsRef<str>>(&mut self, channel: ChannelId, name: T) -> Result<()> {
+    /// Update channel life, performs `channeledit` with the matching `channel_flag_*`
+    pub fn set_channel_life(&mut self, channel: ChannelId, life: ChannelLife) -> Result<()> {
         writeln!(
             &mut self.tx,
-            "channeledit cid={} channel_name={}",
-            channel,escape_arg(name)
+            "channeledit cid={} {}",
+            channel,
+            life.to_raw()
         )?;
         let _ = self.read_response()?;
         Ok(())
@@ -316,26 +1017,31 @@ impl QueryClient {
 
     /// Update client description. If target is none updates this clients description.
     ///
-    /// Performs `clientupdate CLIENT_DESCRIPTION` or `clientedit clid=` with `CLIENT_DESCRIPTION` if target is set.
+    /// Performs `clientupdate client_description` or `clientedit clid=` with `CLIENT_DESCRIPTION` if target is set.
     pub fn update_description<T: AsRef<str>>(
         &mut self,
         descr: T,
         target: Option<ClientId>,
     ) -> Result<()> {
         if let Some(clid) = target {
-            writeln!(
-                &mut self.tx,
-                "clientedit clid={} CLIENT_DESCRIPTION={}",
-                clid,
-                escape_arg(descr)
-            )?;
+            self.client_edit(clid, &[("CLIENT_DESCRIPTION", descr.as_ref().to_owned())])
         } else {
-            writeln!(
-                &mut self.tx,
-                "clientupdate CLIENT_DESCRIPTION={}",
-                escape_arg(descr)
-            )?;
+            self.client_update(&ClientUpdate {
+                client_description: Some(descr.as_ref().to_owned()),
+                ..Default::default()
+            })
+        }
+    }
+
+    /// Edit properties of client `clid`, values are escaped.
+    ///
+    /// Performs `clientedit clid=`
+    pub fn client_edit(&mut self, clid: ClientId, props: &[(&str, String)]) -> Result<()> {
+        let mut cmd = RawCommand::new("clientedit").arg("clid", clid);
+        for (key, value) in props {
+            cmd = cmd.arg_escaped(key, value);
         }
+        writeln!(&mut self.tx, "{}", cmd)?;
         let _ = self.read_response()?;
         Ok(())
     }
@@ -344,12 +1050,10 @@ impl QueryClient {
     ///
     /// Performs `clientpoke`
     pub fn poke_client<T: AsRef<str>>(&mut self, client: ClientId, msg: T) -> Result<()> {
-        writeln!(
-            &mut self.tx,
-            "clientpoke clid={} msg={}",
-            client,
-            msg.as_ref()
-        )?;
+        let cmd = RawCommand::new("clientpoke")
+            .arg("clid", client)
+            .arg_escaped("msg", msg);
+        writeln!(&mut self.tx, "{}", cmd)?;
         let _ = self.read_response()?;
         Ok(())
     }
@@ -366,56 +1070,239 @@ impl QueryClient {
         Ok(())
     }
 
-    /// Send quit command, does not close the socket, not to be exposed
-    fn quit(&mut self) -> Result<()> {
-        writeln!(&mut self.tx, "quit")?;
-        let _ = self.read_response()?;
+    /// Send chat message split into chunks of at most `chunk_len` bytes, so long messages are not truncated.
+    ///
+    /// Chunks are split on character boundaries, `chunk_len` applies to the unescaped message.
+    /// Chunks are sent in order, stops on the first error.
+    pub fn send_message_chunked(
+        &mut self,
+        target: MessageTarget,
+        msg: &str,
+        chunk_len: usize,
+    ) -> Result<()> {
+        for chunk in Self::chunk_message(msg, chunk_len) {
+            self.send_message(target, chunk)?;
+        }
         Ok(())
     }
 
-    /// Inner new-function that handles greeting etc
-    fn new_inner<A: ToSocketAddrs>(
-        addr: A,
-        timeout: Option<Duration>,
-        conn_timeout: Option<Duration>,
-    ) -> Result<(BufReader<TcpStream>, TcpStream)> {
-        let addr = addr
-            .to_socket_addrs()
-            .context(Io {
-                context: "invalid socket address",
-            })?
-            .next()
-            .context(InvalidSocketAddress {})?;
-        let stream = if let Some(dur) = conn_timeout {
-            TcpStream::connect_timeout(&addr, dur).context(Io {
-                context: "while connecting: ",
-            })?
-        } else {
-            TcpStream::connect(addr).context(Io {
-                context: "while connecting: ",
-            })?
+    /// Split `msg` into chunks of at most `chunk_len` bytes on character boundaries.
+    /// Chunks contain at least one character.
+    fn chunk_message(mut msg: &str, chunk_len: usize) -> Vec<&str> {
+        let mut chunks = Vec::new();
+        while !msg.is_empty() {
+            let mut end = chunk_len.min(msg.len());
+            while !msg.is_char_boundary(end) {
+                end -= 1;
+            }
+            if end == 0 {
+                end = msg.chars().next().map_or(msg.len(), char::len_utf8);
+            }
+            let (chunk, rest) = msg.split_at(end);
+            chunks.push(chunk);
+            msg = rest;
+        }
+        chunks
+    }
+
+    /// Send chat message to a client, see [`send_message`](QueryClient::send_message)
+    pub fn message_client<T: AsRef<str>>(&mut self, client: ClientId, msg: T) -> Result<()> {
+        self.send_message(MessageTarget::Client(client), msg)
+    }
+
+    /// Send chat message to the current channel of this client, see [`send_message`](QueryClient::send_message)
+    pub fn message_channel<T: AsRef<str>>(&mut self, msg: T) -> Result<()> {
+        self.send_message(MessageTarget::Channel, msg)
+    }
+
+    /// Send chat message to the whole server, see [`send_message`](QueryClient::send_message)
+    pub fn message_server<T: AsRef<str>>(&mut self, msg: T) -> Result<()> {
+        self.send_message(MessageTarget::Server, msg)
+    }
+
+    /// Send chat message to channel `cid`, moving this client into the channel first.
+    ///
+    /// Channel messages can only be sent to the current channel of a client, see [`MessageTarget::Channel`].
+    pub fn message_to_channel<T: AsRef<str>>(&mut self, cid: ChannelId, msg: T) -> Result<()> {
+        self.join_channel(cid, None)?;
+        self.send_message(MessageTarget::Channel, msg)
+    }
+
+    /// Move this query client into channel `cid` with optional channel password.
+    ///
+    /// Does nothing if the client already is in the channel.
+    ///
+    /// Performs `clientmove` with the (cached) own client ID
+    pub fn join_channel(&mut self, cid: ChannelId, password: Option<&str>) -> Result<()> {
+        let clid = self.own_client_id()?;
+        match self.move_client(clid, cid, password) {
+            Err(e) if e.is_already_member() => {}
+            v => v?,
+        }
+        Ok(())
+    }
+
+    /// Returns the ID of the selected server, 0 if none is selected.
+    ///
+    /// Cached after the first `whoami` until another server is selected.
+    pub fn selected_server(&mut self) -> Result<ServerId> {
+        if let Some(sid) = self.sid {
+            return Ok(sid);
+        }
+        let mut res = self.whoami(false)?;
+        let sid = int_val_parser(&mut res, "virtualserver_id")?;
+        self.sid = Some(sid);
+        Ok(sid)
+    }
+
+    /// Returns the client ID of this connection, cached after the first `whoami`
+    fn own_client_id(&mut self) -> Result<ClientId> {
+        if let Some(clid) = self.clid {
+            return Ok(clid);
+        }
+        let mut res = self.whoami(false)?;
+        let clid = int_val_parser(&mut res, KEY_CLIENT_ID_SELF)?;
+        self.clid = Some(clid);
+        Ok(clid)
+    }
+
+    /// Close connection, performs `logout` if logged in and `quit` before shutting down the socket.
+    ///
+    /// `quit` and the shutdown are always attempted, the first error is returned.
+    /// Preferred over dropping the client, which only performs a best-effort `quit` ignoring all errors.
+    /// Any further command on a TCP connection will fail, streams injected via `from_streams` are not closed.
+    pub fn close(&mut self) -> Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+        self.closed = true;
+        let logout = if self.login.is_some() {
+            self.logout()
+        } else {
+            Ok(())
+        };
+        let quit = self.quit();
+        let shutdown = self.shutdown().context(Io {
+            context: "closing connection: ",
+        });
+        logout.and(quit).and(shutdown)
+    }
+
+    /// Send quit command, does not close the socket, not to be exposed
+    fn quit(&mut self) -> Result<()> {
+        writeln!(&mut self.tx, "quit")?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Create a client on top of the given streams, for example to test bots without a TS3 server.
+    ///
+    /// `reader` has to start with the server greeting of two lines, `TS3` and the welcome message.
+    /// Reconnects are not possible and socket specific functions like [`peer_addr`](QueryClient::peer_addr) fail.
+    /// ```rust
+    /// use ts3_query::*;
+    /// use std::io::{self, Cursor};
+    ///
+    /// # fn main() -> Result<(),Ts3Error> {
+    /// let script = "TS3\n\rWelcome\n\rvirtualserver_status=online client_id=1\n\rerror id=0 msg=ok\n\r";
+    /// let mut client = QueryClient::from_streams(Cursor::new(script), io::sink())?;
+    /// let mut res = client.whoami(false)?;
+    /// assert_eq!(1u16, raw::int_val_parser(&mut res, "client_id")?);
+    /// # Ok(()) }
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "mock")))]
+    #[cfg(feature = "mock")]
+    pub fn from_streams<R, W>(reader: R, writer: W) -> Result<Self>
+    where
+        R: Read + Send + Sync + 'static,
+        W: Write + Send + Sync + 'static,
+    {
+        let mut rx: Reader = BufReader::new(Box::new(reader));
+        Self::read_greeting(&mut rx)?;
+
+        Ok(Self {
+            rx,
+            tx: CommandWriter::new(Box::new(writer)),
+            stream: None,
+            addr: None,
+            conn_timeout: None,
+            timeout: None,
+            greeting_timeout: None,
+            limit_lines: LIMIT_READ_LINES,
+            limit_lines_bytes: LIMIT_LINE_BYTES,
+            limit_total_bytes: LIMIT_TOTAL_BYTES,
+            legacy_tokens: false,
+            lossy_utf8: false,
+            ft_id: 0,
+            closed: false,
+            login: None,
+            selected: None,
+            clid: None,
+            sid: None,
+            uids: VecDeque::new(),
+            events: VecDeque::new(),
+            line_observer: None,
+        })
+    }
+
+    /// Inner new-function that handles greeting etc
+    fn new_inner(
+        addr: &SocketAddr,
+        timeout: Option<Duration>,
+        conn_timeout: Option<Duration>,
+        greeting_timeout: Option<Duration>,
+    ) -> Result<(Reader, Writer, TcpStream)> {
+        let stream = if let Some(dur) = conn_timeout {
+            TcpStream::connect_timeout(addr, dur).context(Io {
+                context: "while connecting: ",
+            })?
+        } else {
+            TcpStream::connect(addr).context(Io {
+                context: "while connecting: ",
+            })?
         };
 
         stream.set_write_timeout(timeout).context(Io {
             context: "setting write timeout: ",
         })?;
-        stream.set_read_timeout(timeout).context(Io {
-            context: "setting read timeout: ",
-        })?;
+        stream
+            .set_read_timeout(greeting_timeout.or(timeout))
+            .context(Io {
+                context: "setting read timeout: ",
+            })?;
 
         stream.set_nodelay(true).context(Io {
             context: "setting nodelay: ",
         })?;
 
-        let mut reader = BufReader::new(stream.try_clone().context(Io {
+        let (mut reader, writer) = split_transport(&stream).context(Io {
             context: "splitting connection: ",
-        })?);
+        })?;
 
+        Self::read_greeting(&mut reader)?;
+
+        if greeting_timeout.is_some() {
+            stream.set_read_timeout(timeout).context(Io {
+                context: "setting read timeout: ",
+            })?;
+        }
+
+        Ok((reader, writer, stream))
+    }
+
+    /// Read server greeting, fails if this is not a query connection
+    fn read_greeting(reader: &mut Reader) -> Result<()> {
         // read server type token
         let mut buffer = Vec::new();
-        reader.read_until(b'\r', &mut buffer).context(Io {
-            context: "reading response: ",
-        })?;
+        reader
+            .read_until(b'\r', &mut buffer)
+            .map_err(Ts3Error::from_io("reading response: "))?;
+        if !buffer.starts_with(b"TS3") {
+            return NotAQueryPort {
+                got: String::from_utf8_lossy(&buffer).trim_end().to_string(),
+            }
+            .fail();
+        }
 
         buffer.clear();
         if let Err(e) = reader.read_until(b'\r', &mut buffer) {
@@ -425,19 +1312,87 @@ impl QueryClient {
                 _ => return Err(e.into()),
             }
         }
-
-        Ok((reader, stream))
+        Ok(())
     }
 
     /// Perform a raw command, returns its response as raw value. (No unescaping is performed.)
     ///
-    /// You need to escape the command properly.
+    /// You need to escape the command properly, see [RawCommand](raw::RawCommand) for a builder doing that.
     pub fn raw_command<T: AsRef<str>>(&mut self, command: T) -> Result<Vec<String>> {
         writeln!(&mut self.tx, "{}", command.as_ref())?;
         let v = self.read_response()?;
         Ok(v)
     }
 
+    /// Perform a raw command that is expected to return no data, only the error line.
+    ///
+    /// Returns [`InvalidResponse`](Ts3Error::InvalidResponse) if any data was returned.
+    /// You need to escape the command properly.
+    pub fn raw_command_expect_empty<T: AsRef<str>>(&mut self, command: T) -> Result<()> {
+        let v = self.raw_command(command)?;
+        if !v.is_empty() {
+            return InvalidResponse {
+                context: "expected empty response, got: ",
+                data: v.join("\n"),
+            }
+            .fail();
+        }
+        Ok(())
+    }
+
+    /// Perform a raw command returning a single entry, returns the unescaped value of `key` parsed as `T`.
+    ///
+    /// You need to escape the command properly.
+    /// ```rust,no_run
+    /// # use ts3_query::*;
+    /// # fn main() -> Result<(),Ts3Error> {
+    /// # let mut client = QueryClient::new("localhost:10011")?;
+    /// let cldbid: ClientDBId = client.get_field("clientgetdbidfromuid cluid=abc", "cldbid")?;
+    /// # Ok(()) }
+    /// ```
+    pub fn get_field<T: FromStr>(&mut self, command: &str, key: &'static str) -> Result<T> {
+        let res = self.raw_command(command)?;
+        let mut response = raw::parse_hashmap(res, true);
+        let v = response
+            .remove(key)
+            .context(NoEntryResponse { key })?
+            .context(NoValueResponse { key })?;
+        match v.parse() {
+            Ok(v) => Ok(v),
+            Err(_) => InvalidResponse {
+                context: "expected parseable value, got: ",
+                data: v,
+            }
+            .fail(),
+        }
+    }
+
+    /// Perform a raw command, returns an iterator over its response lines as raw value. (No unescaping is performed.)
+    ///
+    /// Lines are read while iterating, allowing constant-memory processing of huge responses.
+    /// DoS limits still apply. Iteration ends after the terminating error line,
+    /// which is returned as `Err` item if it contains an error.
+    ///
+    /// Dropping the iterator early reads and discards the remaining response.
+    ///
+    /// You need to escape the command properly.
+    pub fn raw_command_iter<T: AsRef<str>>(
+        &mut self,
+        command: T,
+    ) -> impl Iterator<Item = Result<String>> + '_ {
+        let pending = writeln!(&mut self.tx, "{}", command.as_ref())
+            .err()
+            .map(Ts3Error::from);
+        ResponseIter {
+            done: false,
+            lines: 0,
+            bytes: 0,
+            buffer: Vec::with_capacity(1),
+            pending,
+            client: self,
+        }
+    }
+
     /// Performs `whoami`
     ///
     /// Returns a hashmap of entries. Values are unescaped if set.
@@ -451,21 +1406,29 @@ impl QueryClient {
     pub fn logout(&mut self) -> Result<()> {
         writeln!(&mut self.tx, "logout")?;
         let _ = self.read_response()?;
+        // logout also deselects the server
+        self.login = None;
+        self.selected = None;
+        self.clid = None;
+        self.sid = None;
+        self.uids.clear();
         Ok(())
     }
 
     /// Login with provided data
     ///
-    /// On drop queryclient issues a logout
+    /// On drop queryclient issues a logout.
+    /// The login is stored for [`reconnect`](QueryClient::reconnect).
     pub fn login<T: AsRef<str>, S: AsRef<str>>(&mut self, user: T, password: S) -> Result<()> {
         writeln!(
             &mut self.tx,
             "login {} {}",
-            escape_arg(user),
-            escape_arg(password)
+            escape_arg(&user),
+            escape_arg(&password)
         )?;
 
         let _ = self.read_response()?;
+        self.login = Some((user.as_ref().to_owned(), password.as_ref().to_owned()));
 
         Ok(())
     }
@@ -477,11 +1440,38 @@ impl QueryClient {
         writeln!(&mut self.tx, "use port={}", port)?;
 
         let _ = self.read_response()?;
+        self.selected = Some(ServerSelection::Port(port));
+        self.clid = None;
+        self.sid = None;
+        self.uids.clear();
+        Ok(())
+    }
+
+    /// Select server to perform commands on, by port, setting the nickname of this client at the same time.
+    ///
+    /// Avoids the default query name being visible until a separate [`rename`](QueryClient::rename).
+    ///
+    /// Performs `use port client_nickname`
+    pub fn select_server_by_port_with_name(&mut self, port: u16, nickname: &str) -> Result<()> {
+        writeln!(
+            &mut self.tx,
+            "use port={} client_nickname={}",
+            port,
+            escape_arg(nickname)
+        )?;
+
+        let _ = self.read_response()?;
+        self.selected = Some(ServerSelection::Port(port));
+        self.clid = None;
+        self.sid = None;
+        self.uids.clear();
         Ok(())
     }
 
     /// Move client to channel with optional channel password
     ///
+    /// Fails with [`is_already_member`](Ts3Error::is_already_member) if the client already is in `channel`.
+    ///
     /// Performs `clientmove`
     pub fn move_client(
         &mut self,
@@ -489,40 +1479,123 @@ impl QueryClient {
         channel: ChannelId,
         password: Option<&str>,
     ) -> Result<()> {
-        let pw_arg = if let Some(pw) = password {
-            format!("cpw={}", raw::escape_arg(pw).as_str())
-        } else {
-            String::new()
-        };
-        writeln!(
-            &mut self.tx,
-            "clientmove clid={} cid={} {}",
-            client, channel, pw_arg
-        )?;
+        let mut cmd = format!("clientmove clid={} cid={}", client, channel);
+        if let Some(pw) = password {
+            cmd.push_str(" cpw=");
+            escape_arg_into(pw, &mut cmd);
+        }
+        writeln!(&mut self.tx, "{}", cmd)?;
         let _ = self.read_response()?;
         Ok(())
     }
 
+    /// Move multiple clients to a channel with one bulk `clientmove`, returning the result per client.
+    ///
+    /// The server answers the bulk command with a single error line and does not report which client failed.
+    /// A server error is thus returned for every client, other errors like connection failures abort.
+    pub fn move_clients(
+        &mut self,
+        clients: &[ClientId],
+        channel: ChannelId,
+        password: Option<&str>,
+    ) -> Result<Vec<(ClientId, Result<()>)>> {
+        if clients.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut cmd = String::from("clientmove ");
+        Self::format_ids("clid", clients, &mut cmd);
+        write!(cmd, " cid={}", channel).unwrap();
+        if let Some(pw) = password {
+            cmd.push_str(" cpw=");
+            escape_arg_into(pw, &mut cmd);
+        }
+        writeln!(&mut self.tx, "{}", cmd)?;
+        self.read_bulk_response(clients)
+    }
+
+    /// Kick multiple clients from channel/server with one bulk `clientkick`, returning the result per client.
+    ///
+    /// Server errors are reported for every client like in [`move_clients`](QueryClient::move_clients).
+    pub fn kick_clients(
+        &mut self,
+        clients: &[ClientId],
+        server: bool,
+        message: Option<&str>,
+    ) -> Result<Vec<(ClientId, Result<()>)>> {
+        if clients.is_empty() {
+            return Ok(Vec::new());
+        }
+        let reason = if server {
+            ReasonId::ServerKick
+        } else {
+            ReasonId::ChannelKick
+        };
+        let mut cmd = String::from("clientkick ");
+        Self::format_ids("clid", clients, &mut cmd);
+        write!(cmd, " reasonid={}", reason.to_raw()).unwrap();
+        if let Some(msg) = message {
+            cmd.push_str(" reasonmsg=");
+            escape_arg_into(msg, &mut cmd);
+        }
+        writeln!(&mut self.tx, "{}", cmd)?;
+        self.read_bulk_response(clients)
+    }
+
+    /// Read the response of a bulk command for `clients`, mapping its result to every client
+    fn read_bulk_response(&mut self, clients: &[ClientId]) -> Result<Vec<(ClientId, Result<()>)>> {
+        match self.read_response() {
+            Ok(_) => Ok(clients.iter().map(|c| (*c, Ok(()))).collect()),
+            Err(Ts3Error::ServerError { response, .. }) => Ok(clients
+                .iter()
+                .map(|c| {
+                    let response = ErrorResponse {
+                        id: response.id,
+                        msg: response.msg.clone(),
+                    };
+                    (*c, ServerError { response }.fail())
+                })
+                .collect()),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Kick client with specified message from channel/server. Message can't be longer than 40 characters.
     ///
-    /// Performs `clientkick`
+    /// See [`kick_from_channel`](QueryClient::kick_from_channel) and [`kick_from_server`](QueryClient::kick_from_server).
     pub fn kick_client(
         &mut self,
         client: ClientId,
         server: bool,
         message: Option<&str>,
     ) -> Result<()> {
-        let msg_arg = if let Some(pw) = message {
-            format!("reasonmsg={}", raw::escape_arg(pw).as_str())
+        if server {
+            self.kick_from_server(client, message)
         } else {
-            String::new()
-        };
-        let rid = if server { 5 } else { 4 };
-        writeln!(
-            &mut self.tx,
-            "clientkick clid={} reasonid={} {}",
-            client, rid, msg_arg
-        )?;
+            self.kick_from_channel(client, message)
+        }
+    }
+
+    /// Kick client with specified message from its channel. Message can't be longer than 40 characters.
+    ///
+    /// Performs `clientkick reasonid=4`
+    pub fn kick_from_channel(&mut self, client: ClientId, message: Option<&str>) -> Result<()> {
+        self.kick(client, ReasonId::ChannelKick, message)
+    }
+
+    /// Kick client with specified message from the server. Message can't be longer than 40 characters.
+    ///
+    /// Performs `clientkick reasonid=5`
+    pub fn kick_from_server(&mut self, client: ClientId, message: Option<&str>) -> Result<()> {
+        self.kick(client, ReasonId::ServerKick, message)
+    }
+
+    fn kick(&mut self, client: ClientId, reason: ReasonId, message: Option<&str>) -> Result<()> {
+        let mut cmd = format!("clientkick clid={} reasonid={}", client, reason.to_raw());
+        if let Some(msg) = message {
+            cmd.push_str(" reasonmsg=");
+            escape_arg_into(msg, &mut cmd);
+        }
+        writeln!(&mut self.tx, "{}", cmd)?;
         let _ = self.read_response()?;
         Ok(())
     }
@@ -566,6 +1639,46 @@ impl QueryClient {
         Ok(())
     }
 
+    /// Check whether the connection is still open, without sending a command.
+    ///
+    /// This is best-effort: it only detects connections closed or reset by the server,
+    /// use [`ping`](QueryClient::ping) to verify the server responds.
+    /// Always true for injected streams that weren't [closed](QueryClient::close).
+    pub fn is_connected(&self) -> bool {
+        if self.closed {
+            return false;
+        }
+        let stream = match &self.stream {
+            Some(stream) => stream,
+            None => return true,
+        };
+        // pending data, which could also be a notification
+        if !self.rx.buffer().is_empty() {
+            return true;
+        }
+        if !matches!(stream.take_error(), Ok(None)) {
+            return false;
+        }
+        if stream.set_nonblocking(true).is_err() {
+            return false;
+        }
+        let mut buf = [0; 1];
+        let res = match stream.peek(&mut buf) {
+            // EOF, closed by the server
+            Ok(0) => false,
+            Ok(_) => true,
+            Err(e) => e.kind() == io::ErrorKind::WouldBlock,
+        };
+        stream.set_nonblocking(false).is_ok() && res
+    }
+
+    /// Ping the server like [`ping`](QueryClient::ping), returns the round-trip time
+    pub fn ping_latency(&mut self) -> Result<Duration> {
+        let start = Instant::now();
+        self.ping()?;
+        Ok(start.elapsed())
+    }
+
     /// Select server to perform commands on, by server id.
     ///
     /// Performs `use sid`
@@ -573,6 +1686,10 @@ impl QueryClient {
         writeln!(&mut self.tx, "use sid={}", sid)?;
 
         let _ = self.read_response()?;
+        self.selected = Some(ServerSelection::Id(sid));
+        self.clid = None;
+        self.sid = None;
+        self.uids.clear();
         Ok(())
     }
 
@@ -586,16 +1703,36 @@ impl QueryClient {
         if cldbid.is_empty() {
             return Ok(());
         }
-        writeln!(
-            &mut self.tx,
-            "servergroupdelclient sgid={} {}",
-            group,
-            Self::format_cldbids(cldbid)
-        )?;
+        let mut cmd = format!("servergroupdelclient sgid={} ", group);
+        Self::format_ids("cldbid", cldbid, &mut cmd);
+        writeln!(&mut self.tx, "{}", cmd)?;
         let _ = self.read_response()?;
         Ok(())
     }
 
+    /// Returns all clients of a server group with their client database ID, nickname and unique identifier.
+    /// Values are unescaped.
+    ///
+    /// Performs `servergroupclientlist -names`
+    pub fn servergroup_client_list_named(
+        &mut self,
+        group: ServerGroupID,
+    ) -> Result<Vec<(ClientDBId, String, String)>> {
+        writeln!(&mut self.tx, "servergroupclientlist sgid={} -names", group)?;
+        let res = self.read_list_response()?;
+
+        raw::parse_multi_hashmap_non_empty(res, false)
+            .into_iter()
+            .map(|mut v| {
+                Ok((
+                    int_val_parser(&mut v, "cldbid")?,
+                    string_val_parser(&mut v, "client_nickname")?,
+                    string_val_parser(&mut v, "client_unique_identifier")?,
+                ))
+            })
+            .collect()
+    }
+
     /// Performs `servergroupaddclient`  
     /// Ads all specified `cldbid` clients to `group`.
     pub fn server_group_add_clients(
@@ -606,69 +1743,186 @@ impl QueryClient {
         if cldbid.is_empty() {
             return Ok(());
         }
-        let v = Self::format_cldbids(cldbid);
-        writeln!(&mut self.tx, "servergroupaddclient sgid={} {}", group, v)?;
+        let mut cmd = format!("servergroupaddclient sgid={} ", group);
+        Self::format_ids("cldbid", cldbid, &mut cmd);
+        writeln!(&mut self.tx, "{}", cmd)?;
         let _ = self.read_response()?;
         Ok(())
     }
 
-    /// Turn a list of client-db-ids into a list of cldbid=X
-    fn format_cldbids(it: &[usize]) -> String {
-        // would need itertools for format_with
+    /// Performs `servergroupaddclient`  
+    /// Adds a single client to `group`, see [`server_group_add_clients`](QueryClient::server_group_add_clients).
+    pub fn server_group_add_client(
+        &mut self,
+        group: ServerGroupID,
+        cldbid: ClientDBId,
+    ) -> Result<()> {
+        writeln!(
+            &mut self.tx,
+            "servergroupaddclient sgid={} cldbid={}",
+            group, cldbid
+        )?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Performs `servergroupdelclient`  
+    /// Removes a single client from `group`, see [`server_group_del_clients`](QueryClient::server_group_del_clients).
+    pub fn server_group_del_client(
+        &mut self,
+        group: ServerGroupID,
+        cldbid: ClientDBId,
+    ) -> Result<()> {
+        writeln!(
+            &mut self.tx,
+            "servergroupdelclient sgid={} cldbid={}",
+            group, cldbid
+        )?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
 
-        let mut res = String::new();
+    /// Append a list of IDs as list of key=X to `out`, like `cldbid=1|cldbid=2`
+    fn format_ids<T: fmt::Display>(key: &str, it: &[T], out: &mut String) {
+        // would need itertools for format_with
         let mut it = it.iter();
         if let Some(n) = it.next() {
-            write!(res, "cldbid={}", n).unwrap();
+            write!(out, "{}={}", key, n).unwrap();
         }
         for n in it {
-            write!(res, "|cldbid={}", n).unwrap();
+            write!(out, "|{}={}", key, n).unwrap();
+        }
+    }
+
+    /// Read response and check error line.
+    ///
+    /// Resends the last command once if it was rejected for flooding and anti flood is enabled.
+    fn read_response(&mut self) -> Result<Vec<String>> {
+        #[cfg(feature = "tracing")]
+        let _span = self.tx.span.clone().entered();
+        let res = self.read_response_flood();
+        #[cfg(feature = "tracing")]
+        match &res {
+            Ok(lines) => tracing::debug!(lines = lines.len(), "response"),
+            Err(e) => tracing::debug!(error = %e, "command failed"),
         }
         res
     }
 
+    /// Read response, retrying once on flooding if [`anti_flood`](QueryClient::anti_flood) is set
+    fn read_response_flood(&mut self) -> Result<Vec<String>> {
+        match self.read_response_inner() {
+            Err(e)
+                if self.tx.interval.is_some()
+                    && e.error_response().is_some_and(|r| r.is_flooding()) =>
+            {
+                (self.tx.sleep)(FLOOD_RETRY_WAIT.max(self.tx.interval.unwrap_or_default()));
+                let command = std::mem::take(&mut self.tx.command);
+                self.tx.write_all(&command)?;
+                self.read_response_inner()
+            }
+            v => v,
+        }
+    }
+
     /// Read response and check error line
-    fn read_response(&mut self) -> Result<Vec<String>> {
+    fn read_response_inner(&mut self) -> Result<Vec<String>> {
         let mut result: Vec<String> = Vec::new();
-        let mut lr = (&mut self.rx).take(self.limit_lines_bytes);
+        let mut bytes = 0;
         for _ in 0..self.limit_lines {
-            let mut buffer = Vec::new();
-            // damn cargo fmt..
-            if lr.read_until(b'\r', &mut buffer).context(Io {
-                context: "reading response: ",
-            })? == 0
-            {
-                return ConnectionClosed {}.fail();
+            if !self.read_line(self.limit_lines_bytes, &mut bytes, &mut result)? {
+                return Ok(result);
             }
-            // we read until \r or max-read limit
-            if buffer.ends_with(&[b'\r']) {
+        }
+        ResponseLimit {
+            kind: LimitKind::Lines,
+            response: result,
+        }
+        .fail()
+    }
+
+    /// Read response of a list command, an [empty result](Ts3Error::is_empty_result) error is returned as empty response.
+    fn read_list_response(&mut self) -> Result<Vec<String>> {
+        match self.read_response() {
+            Err(e) if e.is_empty_result() => Ok(Vec::new()),
+            v => v,
+        }
+    }
+
+    /// Read a single response line of at most `limit` bytes and push it to `result` if not empty.
+    ///
+    /// `total` is the amount of bytes read for this response so far, limited by the bytes per response limit.
+    ///
+    /// Returns false if the (valid) error line terminating the response was read.
+    fn read_line(&mut self, limit: u64, total: &mut u64, result: &mut Vec<String>) -> Result<bool> {
+        let remaining = self.limit_total_bytes.saturating_sub(*total);
+        let kind = if remaining < limit {
+            LimitKind::TotalBytes
+        } else {
+            LimitKind::LineBytes
+        };
+        let limit = limit.min(remaining);
+        if limit == 0 {
+            return ResponseLimit {
+                kind,
+                response: std::mem::take(result),
+            }
+            .fail();
+        }
+        let mut lr = (&mut self.rx).take(limit);
+        let mut buffer = Vec::new();
+        let read = lr
+            .read_until(b'\r', &mut buffer)
+            .map_err(Ts3Error::from_io("reading response: "))?;
+        if read == 0 {
+            return ConnectionClosed {}.fail();
+        }
+        *total += read as u64;
+        // we read until \r or max-read limit
+        if buffer.ends_with(b"\r") {
+            buffer.pop();
+            if buffer.ends_with(b"\n") {
                 buffer.pop();
-                if buffer.ends_with(&[b'\n']) {
-                    buffer.pop();
-                }
-            } else if lr.limit() == 0 {
-                return ResponseLimit { response: result }.fail();
-            } else {
-                return InvalidResponse {
-                    context: "expected \\r delimiter, got: ",
-                    data: String::from_utf8_lossy(&buffer),
-                }
-                .fail();
             }
+        } else if lr.limit() == 0 {
+            return ResponseLimit {
+                kind,
+                response: std::mem::take(result),
+            }
+            .fail();
+        } else {
+            return InvalidResponse {
+                context: "expected \\r delimiter, got: ",
+                data: String::from_utf8_lossy(&buffer),
+            }
+            .fail();
+        }
 
-            if !buffer.is_empty() {
-                let line = String::from_utf8(buffer).context(Utf8Error)?;
-                #[cfg(feature = "debug_response")]
-                println!("Read: {:?}", &line);
-                if line.starts_with("error ") {
-                    Self::check_ok(&line)?;
-                    return Ok(result);
+        if !buffer.is_empty() {
+            let line = if self.lossy_utf8 {
+                String::from_utf8_lossy(&buffer).into_owned()
+            } else {
+                String::from_utf8(buffer).context(Utf8Error)?
+            };
+            #[cfg(feature = "debug_response")]
+            println!("Read: {:?}", &line);
+            if let Some(observer) = self.line_observer.as_mut() {
+                observer(&line);
+            }
+            if line.starts_with("error ") {
+                Self::check_ok(&line)?;
+                return Ok(false);
+            }
+            if line.starts_with("notify") {
+                if self.events.len() >= LIMIT_EVENT_QUEUE {
+                    self.events.pop_front();
                 }
-                result.push(line);
+                self.events.push_back(line);
+                return Ok(true);
             }
-            lr.set_limit(LIMIT_LINE_BYTES);
+            result.push(line);
         }
-        ResponseLimit { response: result }.fail()
+        Ok(true)
     }
 
     /// Returns a list of online clients with full infos. Visiblity depends on current permissions. Values are unescaped where applicable.
@@ -679,12 +1933,9 @@ impl QueryClient {
             &mut self.tx,
             "clientlist -uid -away -voice -times -groups -info -country -ip -badges"
         )?;
-        let res = self.read_response()?;
+        let res = self.read_list_response()?;
 
-        let clients = raw::parse_multi_hashmap(res, false)
-            .into_iter()
-            .map(|v| Ok(OnlineClientFull::from_raw(v)?))
-            .collect::<Result<_>>()?;
+        let clients = raw::parse_grouped(res)?;
 
         Ok(clients)
     }
@@ -694,27 +1945,53 @@ impl QueryClient {
     /// Performs `clientlist`
     pub fn online_clients(&mut self) -> Result<Vec<OnlineClient>> {
         writeln!(&mut self.tx, "clientlist")?;
-        let res = self.read_response()?;
+        let res = self.read_list_response()?;
 
-        let clients = raw::parse_multi_hashmap(res, false)
-            .into_iter()
-            .map(|v| Ok(OnlineClient::from_raw(v)?))
-            .collect::<Result<_>>()?;
+        let clients = raw::parse_grouped(res)?;
 
         Ok(clients)
     }
 
+    /// Returns a list of online clients like [`online_clients`](QueryClient::online_clients), excluding query clients.
+    pub fn online_users(&mut self) -> Result<Vec<OnlineClient>> {
+        let mut clients = self.online_clients()?;
+        clients.retain(|c| c.client_type != 1);
+        Ok(clients)
+    }
+
+    /// Returns all online clients with their idle time, sorted by idle time descending.
+    ///
+    /// Performs `clientlist -times`
+    pub fn clients_by_idle(&mut self) -> Result<Vec<(ClientId, Duration)>> {
+        writeln!(&mut self.tx, "clientlist -times")?;
+        let res = self.read_list_response()?;
+
+        let mut clients = raw::parse_multi_hashmap_non_empty(res, false)
+            .into_iter()
+            .map(|mut v| {
+                Ok((
+                    int_val_parser(&mut v, "clid")?,
+                    Duration::from_millis(int_val_parser(&mut v, "client_idle_time")?),
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        clients.sort_by_key(|c| std::cmp::Reverse(c.1));
+        Ok(clients)
+    }
+
+    /// Returns the client with the highest idle time, see [`clients_by_idle`](QueryClient::clients_by_idle)
+    pub fn most_idle_client(&mut self) -> Result<Option<(ClientId, Duration)>> {
+        Ok(self.clients_by_idle()?.into_iter().next())
+    }
+
     /// Returns a list of channels. Values are unescaped where applicable.
     ///
     /// Performs `channellist`
     pub fn channels(&mut self) -> Result<Vec<Channel>> {
         writeln!(&mut self.tx, "channellist")?;
-        let res = self.read_response()?;
+        let res = self.read_list_response()?;
 
-        let channels = raw::parse_multi_hashmap(res, false)
-            .into_iter()
-            .map(|v| Ok(Channel::from_raw(v)?))
-            .collect::<Result<_>>()?;
+        let channels = raw::parse_grouped(res)?;
 
         Ok(channels)
     }
@@ -727,12 +2004,9 @@ impl QueryClient {
             &mut self.tx,
             "channellist -topic -flags -voice -limits -icon -secondsempty"
         )?;
-        let res = self.read_response()?;
+        let res = self.read_list_response()?;
 
-        let channels = raw::parse_multi_hashmap(res, false)
-            .into_iter()
-            .map(|v| Ok(ChannelFull::from_raw(v)?))
-            .collect::<Result<_>>()?;
+        let channels = raw::parse_grouped(res)?;
 
         Ok(channels)
     }
@@ -752,6 +2026,59 @@ impl QueryClient {
         Ok(())
     }
 
+    /// Moves a channel below `new_parent`, optionally placing it after the channel `order`.
+    ///
+    /// Performs `channelmove cid={} cpid={} order={}`
+    pub fn move_channel(
+        &mut self,
+        cid: ChannelId,
+        new_parent: ChannelId,
+        order: Option<ChannelId>,
+    ) -> Result<()> {
+        let order = order.map(|o| format!(" order={}", o)).unwrap_or_default();
+        writeln!(
+            &mut self.tx,
+            "channelmove cid={} cpid={}{}",
+            cid, new_parent, order
+        )?;
+        let _ = self.read_response()?;
+
+        Ok(())
+    }
+
+    /// Edit properties of `channel`, succeeds if nothing changed.
+    ///
+    /// Performs `channeledit`
+    pub fn edit_channel(&mut self, channel: ChannelId, edit: &ChannelEdit) -> Result<()> {
+        writeln!(
+            &mut self.tx,
+            "channeledit cid={}{}",
+            channel,
+            &edit.to_raw()
+        )?;
+        Self::ignore_no_modifications(self.read_response())
+    }
+
+    /// Edit properties of the selected server, values are escaped. Succeeds if nothing changed.
+    ///
+    /// Performs `serveredit`
+    pub fn server_edit(&mut self, props: &[(&str, String)]) -> Result<()> {
+        let mut cmd = RawCommand::new("serveredit");
+        for (key, value) in props {
+            cmd = cmd.arg_escaped(key, value);
+        }
+        writeln!(&mut self.tx, "{}", cmd)?;
+        Self::ignore_no_modifications(self.read_response())
+    }
+
+    /// Map the [no modifications](Ts3Error::is_no_modifications) error of an edit to success
+    fn ignore_no_modifications(res: Result<Vec<String>>) -> Result<()> {
+        match res {
+            Err(e) if e.is_no_modifications() => Ok(()),
+            res => res.map(|_| ()),
+        }
+    }
+
     /// Creates a channel
     /// Performs `channelcreate`
     pub fn create_channel(&mut self, channel: &ChannelEdit) -> Result<ChannelId> {
@@ -769,24 +2096,76 @@ impl QueryClient {
     /// Performs `servergrouplist`
     pub fn server_groups(&mut self) -> Result<Vec<ServerGroup>> {
         writeln!(&mut self.tx, "servergrouplist")?;
-        let res = self.read_response()?;
+        let res = self.read_list_response()?;
 
-        let groups = raw::parse_multi_hashmap(res, false)
-            .into_iter()
-            .map(|v| Ok(ServerGroup::from_raw(v)?))
-            .collect::<Result<_>>()?;
+        let groups = raw::parse_grouped(res)?;
 
         Ok(groups)
     }
 
+    /// Copy server group `source` including its permissions into `target`.
+    ///
+    /// If `target` is 0, a new group with `name` and `group_type` (0 template, 1 regular, 2 query) is created and its ID returned.
+    /// Otherwise the permissions of `target` are overwritten, `name` and `group_type` are ignored.
+    ///
+    /// Performs `servergroupcopy`
+    pub fn server_group_copy(
+        &mut self,
+        source: ServerGroupID,
+        target: ServerGroupID,
+        name: &str,
+        group_type: i32,
+    ) -> Result<Option<ServerGroupID>> {
+        let cmd = RawCommand::new("servergroupcopy")
+            .arg("ssgid", source)
+            .arg("tsgid", target)
+            .arg_escaped("name", name)
+            .arg("type", group_type);
+        writeln!(&mut self.tx, "{}", cmd)?;
+        let res = self.read_response()?;
+        if target != ServerGroupID::default() {
+            return Ok(None);
+        }
+        let mut response = raw::parse_hashmap(res, false);
+        Ok(Some(int_val_parser(&mut response, "sgid")?))
+    }
+
+    /// Duplicate server group `source` with all its permissions into a new regular group named `new_name`.
+    ///
+    /// The new group is created and named by a single `servergroupcopy`, so no partially set up group is left behind on errors.
+    /// Returns the ID of the new group.
+    pub fn clone_server_group(
+        &mut self,
+        source: ServerGroupID,
+        new_name: &str,
+    ) -> Result<ServerGroupID> {
+        self.server_group_copy(source, ServerGroupID::default(), new_name, 1)?
+            .context(NoEntryResponse { key: "sgid" })
+    }
+
+    /// Get a list of client-DB-IDs for a given server group ID
+    ///
+    /// Typed variant of [`servergroup_client_cldbids`](QueryClient::servergroup_client_cldbids), returning [`ClientDBId`](ClientDBId)s.
+    ///
+    /// See `servergroupclientlist`
+    pub fn get_servergroup_client_list(&mut self, group: ServerGroupID) -> Result<Vec<ClientDBId>> {
+        writeln!(&mut self.tx, "servergroupclientlist sgid={}", group)?;
+        let res = self.read_list_response()?;
+
+        raw::parse_multi_hashmap_non_empty(res, false)
+            .into_iter()
+            .map(|mut v| int_val_parser(&mut v, "cldbid"))
+            .collect()
+    }
+
     /// Get a list of client-DB-IDs for a given server group ID
     ///
     /// See `servergroupclientlist`
     pub fn servergroup_client_cldbids(&mut self, group: ServerGroupID) -> Result<Vec<usize>> {
         writeln!(&mut self.tx, "servergroupclientlist sgid={}", group)?;
 
-        let resp = self.read_response()?;
-        if let Some(line) = resp.get(0) {
+        let resp = self.read_list_response()?;
+        if let Some(line) = resp.first() {
             let data: Vec<usize> = line
                 .split('|')
                 .map(|e| {
@@ -811,58 +2190,3216 @@ impl QueryClient {
         }
     }
 
-    /// Check if error line is ok
-    fn check_ok(msg: &str) -> Result<()> {
-        let result: Vec<&str> = msg.split(' ').collect();
-        #[cfg(debug)]
-        {
-            // should only be invoked on `error` lines, sanity check
-            assert_eq!(
-                "check_ok invoked on non-error line",
-                result.get(0),
-                Some(&"error")
-            );
-        }
-        if let (Some(id), Some(msg)) = (result.get(1), result.get(2)) {
-            let split_id: Vec<&str> = id.split('=').collect();
-            let split_msg: Vec<&str> = msg.split('=').collect();
-            if let (Some(id), Some(msg)) = (split_id.get(1), split_msg.get(1)) {
-                let id = id.parse::<usize>().map_err(|_| Ts3Error::InvalidResponse {
-                    context: "expected usize, got ",
-                    data: (*msg).to_string(), // clippy lint
-                })?;
-                if id != 0 {
-                    return ServerError {
-                        response: ErrorResponse {
-                            id,
-                            msg: unescape_val(*msg),
-                        },
-                    }
-                    .fail();
-                } else {
-                    return Ok(());
-                }
-            }
-        }
-        Err(Ts3Error::InvalidResponse {
-            context: "expected id and msg, got ",
-            data: msg.to_string(),
-        })
+    /// Returns a page of the client database, starting at offset `start` with up to `duration` entries.
+    ///
+    /// If `count` is set, also returns the total amount of entries in the client database.
+    ///
+    /// Performs `clientdblist start= duration=` with optional `-count`
+    pub fn client_db_list(
+        &mut self,
+        start: usize,
+        duration: usize,
+        count: bool,
+    ) -> Result<(Vec<ClientDbEntry>, Option<usize>)> {
+        writeln!(
+            &mut self.tx,
+            "clientdblist start={} duration={}{}",
+            start,
+            duration,
+            if count { " -count" } else { "" }
+        )?;
+        let res = self.read_list_response()?;
+
+        let mut entries = raw::parse_multi_hashmap_non_empty(res, false);
+        // count is only part of the first entry
+        let total = match (count, entries.first_mut()) {
+            (true, Some(first)) => Some(int_val_parser(first, "count")?),
+            (true, None) => Some(0),
+            (false, _) => None,
+        };
+        let entries = entries
+            .into_iter()
+            .map(ClientDbEntry::from_raw)
+            .collect::<Result<_>>()?;
+
+        Ok((entries, total))
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    /// Find client database IDs by nickname or unique identifier (if `by_uid` is set).
+    ///
+    /// Performs `clientdbfind pattern=` with optional `-uid`
+    pub fn client_db_find(&mut self, pattern: &str, by_uid: bool) -> Result<Vec<ClientDBId>> {
+        writeln!(
+            &mut self.tx,
+            "clientdbfind pattern={}{}",
+            escape_arg(pattern),
+            if by_uid { " -uid" } else { "" }
+        )?;
+        let res = self.read_list_response()?;
 
-    #[test]
-    fn test_format_cldbids() {
-        let ids = vec![0, 1, 2, 3];
-        assert_eq!(
-            "cldbid=0|cldbid=1|cldbid=2|cldbid=3",
-            QueryClient::format_cldbids(&ids)
-        );
-        assert_eq!("", QueryClient::format_cldbids(&[]));
-        assert_eq!("cldbid=0", QueryClient::format_cldbids(&ids[0..1]));
+        raw::parse_multi_hashmap_non_empty(res, false)
+            .into_iter()
+            .map(|mut v| int_val_parser(&mut v, "cldbid"))
+            .collect()
+    }
+
+    /// Edit client database properties of `cldbid`
+    ///
+    /// Performs `clientdbedit cldbid=`
+    pub fn client_db_edit(&mut self, cldbid: ClientDBId, props: &ClientDbEdit) -> Result<()> {
+        writeln!(
+            &mut self.tx,
+            "clientdbedit cldbid={}{}",
+            cldbid,
+            props.to_raw()
+        )?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Delete client from the client database
+    ///
+    /// Performs `clientdbdelete cldbid=`
+    pub fn client_db_delete(&mut self, cldbid: ClientDBId) -> Result<()> {
+        writeln!(&mut self.tx, "clientdbdelete cldbid={}", cldbid)?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Returns the permission ID for a permission name like `b_serverinstance_help_view`
+    ///
+    /// Performs `permidgetbyname permsid=`
+    pub fn perm_id_by_name(&mut self, name: &str) -> Result<PermissionId> {
+        writeln!(&mut self.tx, "permidgetbyname permsid={}", escape_arg(name))?;
+        let res = self.read_response()?;
+
+        let mut response = raw::parse_hashmap(res, false);
+        int_val_parser(&mut response, "permid")
+    }
+
+    /// Returns a list of all permissions available on the server. Values are unescaped where applicable.
+    ///
+    /// Performs `permissionlist`
+    pub fn permission_list(&mut self) -> Result<Vec<Permission>> {
+        writeln!(&mut self.tx, "permissionlist")?;
+        let res = self.read_response()?;
+
+        raw::parse_multi_hashmap_non_empty(res, false)
+            .into_iter()
+            // skip permission group markers (group_id_end) of newer servers
+            .filter(|v| v.contains_key("permid"))
+            .map(Permission::from_raw)
+            .collect()
+    }
+
+    /// Add or update permission of a server group
+    ///
+    /// Performs `servergroupaddperm`
+    pub fn server_group_add_perm(
+        &mut self,
+        group: ServerGroupID,
+        permid: PermissionId,
+        value: i32,
+        negated: bool,
+        skip: bool,
+    ) -> Result<()> {
+        writeln!(
+            &mut self.tx,
+            "servergroupaddperm sgid={} permid={} permvalue={} permnegated={} permskip={}",
+            group,
+            permid,
+            value,
+            if negated { 1 } else { 0 },
+            if skip { 1 } else { 0 }
+        )?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Remove permission from a server group
+    ///
+    /// Performs `servergroupdelperm`
+    pub fn server_group_del_perm(
+        &mut self,
+        group: ServerGroupID,
+        permid: PermissionId,
+    ) -> Result<()> {
+        writeln!(
+            &mut self.tx,
+            "servergroupdelperm sgid={} permid={}",
+            group, permid
+        )?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Add or update permission of a server group by its name, like `i_channel_needed_join_power`
+    ///
+    /// Performs `servergroupaddperm` with `permsid`, see [`server_group_add_perm`](QueryClient::server_group_add_perm)
+    pub fn server_group_add_perm_named(
+        &mut self,
+        group: ServerGroupID,
+        permsid: &str,
+        value: i32,
+        negated: bool,
+        skip: bool,
+    ) -> Result<()> {
+        let cmd = RawCommand::new("servergroupaddperm")
+            .arg("sgid", group)
+            .arg_escaped("permsid", permsid)
+            .arg("permvalue", value)
+            .arg("permnegated", if negated { 1 } else { 0 })
+            .arg("permskip", if skip { 1 } else { 0 });
+        writeln!(&mut self.tx, "{}", cmd)?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Remove permission from a server group by its name
+    ///
+    /// Performs `servergroupdelperm` with `permsid`, see [`server_group_del_perm`](QueryClient::server_group_del_perm)
+    pub fn server_group_del_perm_named(
+        &mut self,
+        group: ServerGroupID,
+        permsid: &str,
+    ) -> Result<()> {
+        let cmd = RawCommand::new("servergroupdelperm")
+            .arg("sgid", group)
+            .arg_escaped("permsid", permsid);
+        writeln!(&mut self.tx, "{}", cmd)?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Add or update permission of all server groups with auto update type `sgtype`
+    ///
+    /// Performs `servergroupautoaddperm`
+    pub fn server_group_auto_add_perm(
+        &mut self,
+        sgtype: AutoGroupType,
+        permid: PermissionId,
+        value: i32,
+        negated: bool,
+        skip: bool,
+    ) -> Result<()> {
+        writeln!(
+            &mut self.tx,
+            "servergroupautoaddperm sgtype={} permid={} permvalue={} permnegated={} permskip={}",
+            sgtype,
+            permid,
+            value,
+            if negated { 1 } else { 0 },
+            if skip { 1 } else { 0 }
+        )?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Remove permission from all server groups with auto update type `sgtype`
+    ///
+    /// Performs `servergroupautodelperm`
+    pub fn server_group_auto_del_perm(
+        &mut self,
+        sgtype: AutoGroupType,
+        permid: PermissionId,
+    ) -> Result<()> {
+        writeln!(
+            &mut self.tx,
+            "servergroupautodelperm sgtype={} permid={}",
+            sgtype, permid
+        )?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Add or update permission of a channel group. Channel groups do not support negated or skip flags.
+    ///
+    /// Performs `channelgroupaddperm`
+    pub fn channel_group_add_perm(
+        &mut self,
+        group: ChannelGroupId,
+        permid: PermissionId,
+        value: i32,
+    ) -> Result<()> {
+        writeln!(
+            &mut self.tx,
+            "channelgroupaddperm cgid={} permid={} permvalue={}",
+            group, permid, value
+        )?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Remove permission from a channel group
+    ///
+    /// Performs `channelgroupdelperm`
+    pub fn channel_group_del_perm(
+        &mut self,
+        group: ChannelGroupId,
+        permid: PermissionId,
+    ) -> Result<()> {
+        writeln!(
+            &mut self.tx,
+            "channelgroupdelperm cgid={} permid={}",
+            group, permid
+        )?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Add or update permission of a channel. Channels do not support negated or skip flags.
+    ///
+    /// Performs `channeladdperm`
+    pub fn channel_add_perm(
+        &mut self,
+        channel: ChannelId,
+        permid: PermissionId,
+        value: i32,
+    ) -> Result<()> {
+        writeln!(
+            &mut self.tx,
+            "channeladdperm cid={} permid={} permvalue={}",
+            channel, permid, value
+        )?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Remove permission from a channel
+    ///
+    /// Performs `channeldelperm`
+    pub fn channel_del_perm(&mut self, channel: ChannelId, permid: PermissionId) -> Result<()> {
+        writeln!(
+            &mut self.tx,
+            "channeldelperm cid={} permid={}",
+            channel, permid
+        )?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Add or update permission of a client. Clients do not support the negated flag.
+    ///
+    /// Performs `clientaddperm`
+    pub fn client_add_perm(
+        &mut self,
+        cldbid: ClientDBId,
+        permid: PermissionId,
+        value: i32,
+        skip: bool,
+    ) -> Result<()> {
+        writeln!(
+            &mut self.tx,
+            "clientaddperm cldbid={} permid={} permvalue={} permskip={}",
+            cldbid,
+            permid,
+            value,
+            if skip { 1 } else { 0 }
+        )?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Remove permission from a client
+    ///
+    /// Performs `clientdelperm`
+    pub fn client_del_perm(&mut self, cldbid: ClientDBId, permid: PermissionId) -> Result<()> {
+        writeln!(
+            &mut self.tx,
+            "clientdelperm cldbid={} permid={}",
+            cldbid, permid
+        )?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Returns the permissions of a server group. If `permsid` is set, permissions are returned by name.
+    ///
+    /// Performs `servergrouppermlist sgid=` with optional `-permsid`
+    pub fn server_group_perm_list(
+        &mut self,
+        group: ServerGroupID,
+        permsid: bool,
+    ) -> Result<Vec<GroupPerm>> {
+        writeln!(
+            &mut self.tx,
+            "servergrouppermlist sgid={}{}",
+            group,
+            if permsid { " -permsid" } else { "" }
+        )?;
+        let res = self.read_list_response()?;
+
+        raw::parse_grouped(res)
+    }
+
+    /// Returns the permissions of a channel group. If `permsid` is set, permissions are returned by name.
+    ///
+    /// Performs `channelgrouppermlist cgid=` with optional `-permsid`
+    pub fn channel_group_perm_list(
+        &mut self,
+        group: ChannelGroupId,
+        permsid: bool,
+    ) -> Result<Vec<GroupPerm>> {
+        writeln!(
+            &mut self.tx,
+            "channelgrouppermlist cgid={}{}",
+            group,
+            if permsid { " -permsid" } else { "" }
+        )?;
+        let res = self.read_list_response()?;
+
+        raw::parse_grouped(res)
+    }
+
+    /// Returns the files and directories at `path` inside the channel, has to be a valid path starting with `/`.
+    /// Returns an empty list for empty directories.
+    ///
+    /// Performs `ftgetfilelist`
+    pub fn file_list(
+        &mut self,
+        channel: ChannelId,
+        path: &str,
+        cpw: Option<&str>,
+    ) -> Result<Vec<FileEntry>> {
+        writeln!(
+            &mut self.tx,
+            "ftgetfilelist cid={} cpw={} path={}",
+            channel,
+            escape_arg(cpw.unwrap_or_default()),
+            escape_arg(path)
+        )?;
+        let res = match self.read_response() {
+            Err(e) if e.error_response().is_some_and(|r| r.id == ERR_NO_FILES) => {
+                return Ok(Vec::new())
+            }
+            v => v?,
+        };
+
+        raw::parse_grouped(res)
+    }
+
+    /// Returns information about the file `name` in directory `path` inside the channel.
+    ///
+    /// Performs `ftgetfileinfo`
+    pub fn file_info(&mut self, channel: ChannelId, path: &str, name: &str) -> Result<FileEntry> {
+        let name = if path.ends_with('/') {
+            format!("{}{}", path, name)
+        } else {
+            format!("{}/{}", path, name)
+        };
+        writeln!(
+            &mut self.tx,
+            "ftgetfileinfo cid={} cpw= name={}",
+            channel,
+            escape_arg(name)
+        )?;
+        let res = self.read_response()?;
+
+        FileEntry::from_raw(raw::parse_hashmap(res, false))
+    }
+
+    /// Returns the next client file transfer ID for this connection
+    fn next_ft_id(&mut self) -> u16 {
+        self.ft_id = self.ft_id.wrapping_add(1);
+        self.ft_id
+    }
+
+    /// Initialize download of file at `path` in channel, starting at `seekpos`.
+    ///
+    /// The returned [`FileTransfer`](FileTransfer) contains the key and port for the file transfer connection.
+    ///
+    /// Performs `ftinitdownload`
+    pub fn file_init_download(
+        &mut self,
+        channel: ChannelId,
+        path: &str,
+        cpw: Option<&str>,
+        seekpos: u64,
+    ) -> Result<FileTransfer> {
+        let ft_id = self.next_ft_id();
+        writeln!(
+            &mut self.tx,
+            "ftinitdownload clientftfid={} name={} cid={} cpw={} seekpos={}",
+            ft_id,
+            escape_arg(path),
+            channel,
+            escape_arg(cpw.unwrap_or_default()),
+            seekpos
+        )?;
+        let res = self.read_response()?;
+
+        FileTransfer::from_raw(raw::parse_hashmap(res, false))
+    }
+
+    /// Initialize upload of file to `path` in channel with `size` bytes.
+    ///
+    /// If `resume` is set, the returned [`FileTransfer`](FileTransfer) contains the `seekpos` to resume from.
+    ///
+    /// Performs `ftinitupload`
+    pub fn file_init_upload(
+        &mut self,
+        channel: ChannelId,
+        path: &str,
+        size: u64,
+        overwrite: bool,
+        resume: bool,
+    ) -> Result<FileTransfer> {
+        let ft_id = self.next_ft_id();
+        writeln!(
+            &mut self.tx,
+            "ftinitupload clientftfid={} name={} cid={} cpw= size={} overwrite={} resume={}",
+            ft_id,
+            escape_arg(path),
+            channel,
+            size,
+            if overwrite { 1 } else { 0 },
+            if resume { 1 } else { 0 }
+        )?;
+        let res = self.read_response()?;
+
+        FileTransfer::from_raw(raw::parse_hashmap(res, false))
+    }
+
+    /// Returns up to `lines` (1-100) log entries of the selected virtual server or the instance log if `instance` is set.
+    ///
+    /// `begin_pos` can be used to continue reading from a previous [`LogView::last_pos`](LogView::last_pos).
+    ///
+    /// Performs `logview`
+    pub fn log_view(
+        &mut self,
+        lines: usize,
+        reverse: bool,
+        instance: bool,
+        begin_pos: Option<u64>,
+    ) -> Result<LogView> {
+        let begin_arg = if let Some(pos) = begin_pos {
+            format!(" begin_pos={}", pos)
+        } else {
+            String::new()
+        };
+        writeln!(
+            &mut self.tx,
+            "logview lines={} reverse={} instance={}{}",
+            lines,
+            if reverse { 1 } else { 0 },
+            if instance { 1 } else { 0 },
+            begin_arg
+        )?;
+        let res = self.read_response()?;
+
+        LogView::from_raw(raw::parse_multi_hashmap_non_empty(res, false))
+    }
+
+    /// Write custom entry into the server log. Level is one of 1 (error), 2 (warning), 3 (debug), 4 (info)
+    ///
+    /// Performs `logadd`
+    pub fn log_add(&mut self, level: u8, msg: &str) -> Result<()> {
+        writeln!(
+            &mut self.tx,
+            "logadd loglevel={} logmsg={}",
+            level,
+            escape_arg(msg)
+        )?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Returns the IPs the server is bound to. `subsystem` can be one of `voice`, `query` or `filetransfer`,
+    /// defaults to `voice` on the server side.
+    ///
+    /// Performs `bindinglist`
+    pub fn binding_list(&mut self, subsystem: Option<&str>) -> Result<Vec<String>> {
+        let mut cmd = String::from("bindinglist");
+        if let Some(v) = subsystem {
+            cmd.push_str(" subsystem=");
+            escape_arg_into(v, &mut cmd);
+        }
+        writeln!(&mut self.tx, "{}", cmd)?;
+        let res = self.read_response()?;
+
+        raw::parse_multi_hashmap_non_empty(res, false)
+            .into_iter()
+            .map(|mut v| string_val_parser(&mut v, "ip"))
+            .collect()
+    }
+
+    /// Returns information about the selected virtual server. Values are unescaped where applicable.
+    ///
+    /// Performs `serverinfo`
+    pub fn server_info(&mut self) -> Result<ServerInfo> {
+        writeln!(&mut self.tx, "serverinfo")?;
+        let res = self.read_response()?;
+
+        ServerInfo::from_raw(raw::parse_hashmap(res, false))
+    }
+
+    /// Returns information about the server instance
+    ///
+    /// Performs `hostinfo`
+    pub fn host_info(&mut self) -> Result<HostInfo> {
+        writeln!(&mut self.tx, "hostinfo")?;
+        let res = self.read_response()?;
+
+        HostInfo::from_raw(raw::parse_hashmap(res, false))
+    }
+
+    /// Returns connection statistics of the selected virtual server
+    ///
+    /// Performs `serverrequestconnectioninfo`
+    pub fn connection_info(&mut self) -> Result<ConnectionInfo> {
+        writeln!(&mut self.tx, "serverrequestconnectioninfo")?;
+        let res = self.read_response()?;
+
+        ConnectionInfo::from_raw(raw::parse_hashmap(res, false))
+    }
+
+    /// Returns the unique identifier of an online client.
+    ///
+    /// Results are cached for the last few clients until the server selection changes or the connection is re-established.
+    /// Note that client IDs are reused by the server after a client disconnected.
+    ///
+    /// Performs `clientinfo clid=`
+    pub fn client_uid_from_clid(&mut self, client: ClientId) -> Result<String> {
+        if let Some(pos) = self.uids.iter().position(|(clid, _)| *clid == client) {
+            let entry = self.uids.remove(pos).unwrap();
+            let uid = entry.1.clone();
+            self.uids.push_front(entry);
+            return Ok(uid);
+        }
+        writeln!(&mut self.tx, "clientinfo clid={}", client)?;
+        let res = self.read_response()?;
+        let uid = string_val_parser(
+            &mut raw::parse_hashmap(res, false),
+            "client_unique_identifier",
+        )?;
+        self.uids.truncate(UID_CACHE_SIZE - 1);
+        self.uids.push_front((client, uid.clone()));
+        Ok(uid)
+    }
+
+    /// Returns connection statistics of an online client
+    ///
+    /// Performs `clientinfo clid=`
+    pub fn client_connection_info(&mut self, client: ClientId) -> Result<ClientConnectionInfo> {
+        writeln!(&mut self.tx, "clientinfo clid={}", client)?;
+        let res = self.read_response()?;
+
+        ClientConnectionInfo::from_raw(raw::parse_hashmap(res, false))
+    }
+
+    /// Create query login credentials for the current client with login name `name`.
+    ///
+    /// Returns the login name and the generated password.
+    /// If the name is already taken, a [`ServerError`](Ts3Error::ServerError) with [`is_duplicate_entry`](ErrorResponse::is_duplicate_entry)
+    /// is returned. Some server versions return an invalid parameter error (1538) instead.
+    ///
+    /// Performs `clientsetserverquerylogin`
+    pub fn create_query_login(&mut self, name: &str) -> Result<(String, String)> {
+        writeln!(
+            &mut self.tx,
+            "clientsetserverquerylogin client_login_name={}",
+            escape_arg(name)
+        )?;
+        let res = self.read_response()?;
+        let mut response = raw::parse_hashmap(res, false);
+        let login = if response.contains_key("client_login_name") {
+            string_val_parser(&mut response, "client_login_name")?
+        } else {
+            name.to_owned()
+        };
+        let password = string_val_parser(&mut response, "client_login_password")?;
+        Ok((login, password))
+    }
+
+    /// Returns a page of query logins, starting at offset `start` with up to `duration` entries.
+    ///
+    /// `pattern` filters login names, `%` can be used as wildcard.
+    ///
+    /// Performs `queryloginlist`
+    pub fn query_login_list(
+        &mut self,
+        pattern: Option<&str>,
+        start: usize,
+        duration: usize,
+    ) -> Result<Vec<QueryLogin>> {
+        let mut cmd = RawCommand::new("queryloginlist");
+        if let Some(pattern) = pattern {
+            cmd = cmd.arg_escaped("pattern", pattern);
+        }
+        let cmd = cmd.arg("start", start).arg("duration", duration);
+        writeln!(&mut self.tx, "{}", cmd)?;
+        let res = self.read_list_response()?;
+
+        raw::parse_grouped(res)
+    }
+
+    /// Create query login credentials with login name `name`, for client `cldbid` or the current one.
+    ///
+    /// Returns the login name and the generated password.
+    ///
+    /// Performs `queryloginadd`
+    pub fn query_login_add(
+        &mut self,
+        name: &str,
+        cldbid: Option<ClientDBId>,
+    ) -> Result<(String, String)> {
+        let mut cmd = RawCommand::new("queryloginadd").arg_escaped("client_login_name", name);
+        if let Some(cldbid) = cldbid {
+            cmd = cmd.arg("cldbid", cldbid);
+        }
+        writeln!(&mut self.tx, "{}", cmd)?;
+        let res = self.read_response()?;
+        let mut response = raw::parse_hashmap(res, false);
+        let login = string_val_parser(&mut response, "client_login_name")?;
+        let password = string_val_parser(&mut response, "client_login_password")?;
+        Ok((login, password))
+    }
+
+    /// Delete the query login of client `cldbid`
+    ///
+    /// Performs `querylogindel`
+    pub fn query_login_del(&mut self, cldbid: ClientDBId) -> Result<()> {
+        writeln!(&mut self.tx, "querylogindel cldbid={}", cldbid)?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Create a new query API key with `scope`, valid for `lifetime_days` (unlimited if `None`).
+    /// If `cldbid` is set, the key is created for that client instead of the current one.
+    ///
+    /// Returns the API key.
+    ///
+    /// Performs `apikeyadd`
+    pub fn api_key_add(
+        &mut self,
+        scope: ApiScope,
+        lifetime_days: Option<u32>,
+        cldbid: Option<ClientDBId>,
+    ) -> Result<String> {
+        let cldbid_arg = if let Some(id) = cldbid {
+            format!(" cldbid={}", id)
+        } else {
+            String::new()
+        };
+        writeln!(
+            &mut self.tx,
+            "apikeyadd scope={} lifetime={}{}",
+            scope,
+            lifetime_days.unwrap_or(0),
+            cldbid_arg
+        )?;
+        let res = self.read_response()?;
+
+        let mut response = raw::parse_hashmap(res, false);
+        string_val_parser(&mut response, "apikey")
+    }
+
+    /// Returns the API keys of `cldbid` or all API keys if `None`.
+    ///
+    /// Performs `apikeylist`
+    pub fn api_key_list(&mut self, cldbid: Option<ClientDBId>) -> Result<Vec<ApiKey>> {
+        if let Some(id) = cldbid {
+            writeln!(&mut self.tx, "apikeylist cldbid={}", id)?;
+        } else {
+            writeln!(&mut self.tx, "apikeylist cldbid=*")?;
+        }
+        let res = self.read_list_response()?;
+
+        raw::parse_grouped(res)
+    }
+
+    /// Delete API key by its ID
+    ///
+    /// Performs `apikeydel`
+    pub fn api_key_del(&mut self, id: usize) -> Result<()> {
+        writeln!(&mut self.tx, "apikeydel id={}", id)?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Create a snapshot of the selected virtual server, returns the raw (escaped) snapshot data.
+    ///
+    /// Snapshots can get huge, the bytes per line limit is raised to at least [`LIMIT_SNAPSHOT_BYTES`](LIMIT_SNAPSHOT_BYTES) while reading it.
+    /// Set a higher limit via [`limit_line_bytes`](QueryClient::limit_line_bytes) for bigger servers.
+    ///
+    /// Performs `serversnapshotcreate`
+    pub fn snapshot_create(&mut self) -> Result<String> {
+        writeln!(&mut self.tx, "serversnapshotcreate")?;
+        let limit = self.limit_lines_bytes.max(LIMIT_SNAPSHOT_BYTES);
+        let res = self.with_line_byte_limit(limit, |c| c.read_response())?;
+
+        Ok(res.concat())
+    }
+
+    /// Deploy a snapshot on the selected virtual server, `data` has to be the raw snapshot as returned by
+    /// [`snapshot_create`](QueryClient::snapshot_create).
+    ///
+    /// Performs `serversnapshotdeploy`
+    pub fn snapshot_deploy(&mut self, data: &str) -> Result<()> {
+        writeln!(&mut self.tx, "serversnapshotdeploy {}", data)?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Find online clients whose nickname contains `pattern`. Returns their client ID and nickname.
+    ///
+    /// Performs `clientfind pattern=`
+    pub fn client_find(&mut self, pattern: &str) -> Result<Vec<(ClientId, String)>> {
+        writeln!(&mut self.tx, "clientfind pattern={}", escape_arg(pattern))?;
+        let res = match self.read_response() {
+            Err(e) if e.error_response().is_some_and(|r| r.is_invalid_client_id()) => {
+                return Ok(Vec::new())
+            }
+            v => v?,
+        };
+
+        raw::parse_multi_hashmap_non_empty(res, false)
+            .into_iter()
+            .map(|mut v| {
+                Ok((
+                    int_val_parser(&mut v, "clid")?,
+                    string_val_parser(&mut v, "client_nickname")?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Find channels whose name contains `pattern`. Returns their channel ID and name.
+    ///
+    /// Performs `channelfind pattern=`
+    pub fn channel_find(&mut self, pattern: &str) -> Result<Vec<(ChannelId, String)>> {
+        writeln!(&mut self.tx, "channelfind pattern={}", escape_arg(pattern))?;
+        let res = match self.read_response() {
+            Err(e)
+                if e.error_response()
+                    .is_some_and(|r| r.id == ERR_INVALID_CHANNEL_ID) =>
+            {
+                return Ok(Vec::new())
+            }
+            v => v?,
+        };
+
+        raw::parse_multi_hashmap_non_empty(res, false)
+            .into_iter()
+            .map(|mut v| {
+                Ok((
+                    int_val_parser(&mut v, "cid")?,
+                    string_val_parser(&mut v, "channel_name")?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Returns full infos of a single channel. Values are unescaped where applicable.
+    ///
+    /// `channelinfo` doesn't return the channel ID and client counters,
+    /// `cid` is taken from the request and missing counters are set to 0.
+    ///
+    /// Performs `channelinfo cid=`
+    pub fn channel_info(&mut self, cid: ChannelId) -> Result<ChannelFull> {
+        writeln!(&mut self.tx, "channelinfo cid={}", cid)?;
+        let res = self.read_response()?;
+
+        let mut response = raw::parse_hashmap(res, false);
+        response.insert("cid".to_string(), Some(cid.to_string()));
+        for key in &[
+            "total_clients",
+            "total_clients_family",
+            "channel_needed_subscribe_power",
+        ] {
+            response
+                .entry(key.to_string())
+                .or_insert_with(|| Some("0".to_string()));
+        }
+        ChannelFull::from_raw(response)
+    }
+
+    /// Returns the oldest queued notification, if any.
+    ///
+    /// Notifications are queued while reading command responses, this does not read from the connection.
+    /// At most [`LIMIT_EVENT_QUEUE`] notifications are kept.
+    /// Returns an [`InvalidResponse`](Ts3Error::InvalidResponse) error for notifications unknown to [`Event`](event::Event).
+    pub fn poll_event(&mut self) -> Result<Option<event::Event>> {
+        match self.events.pop_front() {
+            Some(line) => event::Event::parse(&line).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Register for notifications of `event`.
+    ///
+    /// Notifications received while reading responses are queued, see [`poll_event`](QueryClient::poll_event).
+    ///
+    /// Performs `servernotifyregister`
+    pub fn register_event(&mut self, event: NotifyEvent) -> Result<()> {
+        writeln!(&mut self.tx, "servernotifyregister {}", event)?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Set the channel group of client `cldbid` in channel `channel`
+    ///
+    /// Performs `setclientchannelgroup`
+    pub fn set_client_channel_group(
+        &mut self,
+        group: ChannelGroupId,
+        channel: ChannelId,
+        cldbid: ClientDBId,
+    ) -> Result<()> {
+        writeln!(
+            &mut self.tx,
+            "setclientchannelgroup cgid={} cid={} cldbid={}",
+            group, channel, cldbid
+        )?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Set the channel group of all `cldbids` in channel `channel`, one client at a time.
+    ///
+    /// Stops on the first server error, returns the amount of clients updated successfully.
+    pub fn set_client_channel_group_bulk(
+        &mut self,
+        group: ChannelGroupId,
+        channel: ChannelId,
+        cldbids: &[ClientDBId],
+    ) -> Result<usize> {
+        for (done, cldbid) in cldbids.iter().enumerate() {
+            match self.set_client_channel_group(group, channel, *cldbid) {
+                Err(e) if e.is_error_response() => return Ok(done),
+                v => v?,
+            }
+        }
+        Ok(cldbids.len())
+    }
+
+    /// Returns all effective permissions of client `cldbid` in channel `channel`, including their source.
+    ///
+    /// Performs `permoverview cid= cldbid= permid=0`
+    pub fn perm_overview(
+        &mut self,
+        channel: ChannelId,
+        cldbid: ClientDBId,
+    ) -> Result<Vec<PermOverview>> {
+        writeln!(
+            &mut self.tx,
+            "permoverview cid={} cldbid={} permid=0",
+            channel, cldbid
+        )?;
+        let res = self.read_list_response()?;
+
+        raw::parse_grouped(res)
+    }
+
+    /// Returns the privilege key command for `action`, see [`legacy_tokens`](QueryClient::legacy_tokens)
+    fn token_command(&self, action: &str) -> String {
+        if self.legacy_tokens {
+            format!("token{}", action)
+        } else {
+            format!("privilegekey{}", action)
+        }
+    }
+
+    /// Create a privilege key (token) for `target`, returns the new key
+    ///
+    /// Performs `privilegekeyadd` or `tokenadd`
+    pub fn privilege_key_add(
+        &mut self,
+        target: PrivilegeKeyTarget,
+        description: Option<&str>,
+    ) -> Result<String> {
+        let mut cmd = format!("{} {}", self.token_command("add"), target);
+        if let Some(d) = description {
+            cmd.push_str(" tokendescription=");
+            escape_arg_into(d, &mut cmd);
+        }
+        writeln!(&mut self.tx, "{}", cmd)?;
+        let res = self.read_response()?;
+
+        let mut response = raw::parse_hashmap(res, false);
+        string_val_parser(&mut response, "token")
+    }
+
+    /// Returns all privilege keys (tokens) of the selected server
+    ///
+    /// Performs `privilegekeylist` or `tokenlist`
+    pub fn privilege_key_list(&mut self) -> Result<Vec<PrivilegeKey>> {
+        let cmd = self.token_command("list");
+        writeln!(&mut self.tx, "{}", cmd)?;
+        let res = self.read_list_response()?;
+
+        raw::parse_grouped(res)
+    }
+
+    /// Delete a privilege key (token)
+    ///
+    /// Performs `privilegekeydelete` or `tokendelete`
+    pub fn privilege_key_delete(&mut self, token: &str) -> Result<()> {
+        let cmd = self.token_command("delete");
+        writeln!(&mut self.tx, "{} token={}", cmd, escape_arg(token))?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Use a privilege key (token) to gain the permissions of its group
+    ///
+    /// Performs `privilegekeyuse` or `tokenuse`
+    pub fn privilege_key_use(&mut self, token: &str) -> Result<()> {
+        let cmd = self.token_command("use");
+        writeln!(&mut self.tx, "{} token={}", cmd, escape_arg(token))?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Unregister from all notifications
+    ///
+    /// Performs `servernotifyunregister`
+    pub fn unregister_all_events(&mut self) -> Result<()> {
+        writeln!(&mut self.tx, "servernotifyunregister")?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Change instance properties, values are escaped. See [`InstanceEdit`](InstanceEdit) for common properties.
+    ///
+    /// Performs `instanceedit`
+    pub fn instance_edit(&mut self, props: &[(&str, String)]) -> Result<()> {
+        let mut cmd = String::from("instanceedit");
+        for (key, value) in props {
+            write!(cmd, " {}=", key).unwrap();
+            escape_arg_into(value, &mut cmd);
+        }
+        writeln!(&mut self.tx, "{}", cmd)?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Stop the whole server instance, including all virtual servers, with an optional reason.
+    ///
+    /// **This shuts down the server process**, it has to be restarted outside of the query.
+    ///
+    /// Performs `serverprocessstop`
+    #[cfg_attr(docsrs, doc(cfg(feature = "dangerous-instance-control")))]
+    #[cfg(feature = "dangerous-instance-control")]
+    pub fn instance_stop(&mut self, reason: Option<&str>) -> Result<()> {
+        let mut cmd = String::from("serverprocessstop");
+        if let Some(r) = reason {
+            cmd.push_str(" reasonmsg=");
+            escape_arg_into(r, &mut cmd);
+        }
+        writeln!(&mut self.tx, "{}", cmd)?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Restart the whole server instance, including all virtual servers, with an optional reason.
+    ///
+    /// **This disconnects all clients**, including this query client.
+    ///
+    /// Performs `serverprocessrestart`
+    #[cfg_attr(docsrs, doc(cfg(feature = "dangerous-instance-control")))]
+    #[cfg(feature = "dangerous-instance-control")]
+    pub fn instance_restart(&mut self, reason: Option<&str>) -> Result<()> {
+        let mut cmd = String::from("serverprocessrestart");
+        if let Some(r) = reason {
+            cmd.push_str(" reasonmsg=");
+            escape_arg_into(r, &mut cmd);
+        }
+        writeln!(&mut self.tx, "{}", cmd)?;
+        let _ = self.read_response()?;
+        Ok(())
+    }
+
+    /// Reset all permissions of the selected server to their defaults.
+    ///
+    /// **This removes all custom groups and permissions** and can't be undone.
+    /// Returns the new privilege key for the server admin group, it is the only way to regain admin access.
+    ///
+    /// Performs `permreset`
+    #[cfg_attr(docsrs, doc(cfg(feature = "dangerous-instance-control")))]
+    #[cfg(feature = "dangerous-instance-control")]
+    pub fn permission_reset(&mut self) -> Result<String> {
+        writeln!(&mut self.tx, "permreset")?;
+        let res = self.read_response()?;
+        string_val_parser(&mut raw::parse_hashmap(res, false), "token")
+    }
+
+    /// Check if error line is ok
+    fn check_ok(msg: &str) -> Result<()> {
+        let result: Vec<&str> = msg.split(' ').collect();
+        if let (Some(id), Some(msg)) = (result.get(1), result.get(2)) {
+            let split_id: Vec<&str> = id.split('=').collect();
+            let split_msg: Vec<&str> = msg.split('=').collect();
+            if let (Some(id), Some(msg)) = (split_id.get(1), split_msg.get(1)) {
+                let id = id.parse::<usize>().map_err(|_| Ts3Error::InvalidResponse {
+                    context: "expected usize, got ",
+                    data: (*msg).to_string(), // clippy lint
+                })?;
+                if id != 0 {
+                    return ServerError {
+                        response: ErrorResponse {
+                            id,
+                            msg: unescape_val(*msg),
+                        },
+                    }
+                    .fail();
+                } else {
+                    return Ok(());
+                }
+            }
+        }
+        Err(Ts3Error::InvalidResponse {
+            context: "expected id and msg, got ",
+            data: msg.to_string(),
+        })
+    }
+}
+
+/// Instance control is not available without the `dangerous-instance-control` feature.
+///
+/// ```rust,compile_fail
+/// # fn stop(client: &mut ts3_query::QueryClient) {
+/// client.instance_stop(None);
+/// # }
+/// ```
+/// ```rust,compile_fail
+/// # fn restart(client: &mut ts3_query::QueryClient) {
+/// client.instance_restart(None);
+/// # }
+/// ```
+#[cfg(all(doctest, not(feature = "dangerous-instance-control")))]
+struct InstanceControlGated;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread::{self, JoinHandle};
+
+    /// Terminating line of a successful response
+    pub(crate) const OK: &str = "error id=0 msg=ok\n\r";
+
+    // ID constructors, typed the same with and without strict-ids
+    #[allow(clippy::useless_conversion)]
+    pub(crate) fn client_id(id: u16) -> ClientId {
+        id.into()
+    }
+    #[allow(clippy::useless_conversion)]
+    pub(crate) fn channel_id(id: u64) -> ChannelId {
+        id.into()
+    }
+    #[allow(clippy::useless_conversion)]
+    pub(crate) fn group_id(id: u64) -> ServerGroupID {
+        id.into()
+    }
+    #[allow(clippy::useless_conversion)]
+    pub(crate) fn db_id(id: u64) -> ClientDBId {
+        id.into()
+    }
+    /// Default `whoami` response of the mock server, server on port 9987 selected
+    pub(crate) const WHOAMI: &str = "virtualserver_status=online virtualserver_id=1 virtualserver_unique_identifier=abc= virtualserver_port=9987 client_id=1 client_channel_id=1 client_nickname=serveradmin client_database_id=1 client_login_name=serveradmin client_unique_identifier=serveradmin client_origin_server_id=0\n\rerror id=0 msg=ok\n\r";
+
+    /// Spawn a mock query server and connect to it.
+    ///
+    /// Every received command is answered with the next of `responses`,
+    /// which have to include the terminating error line.
+    /// Once exhausted every command is answered with [`OK`], or [`WHOAMI`] for `whoami`.
+    ///
+    /// The handle returns all received commands after the client disconnected.
+    pub(crate) fn mock_client(responses: &[&str]) -> (QueryClient, JoinHandle<Vec<String>>) {
+        let (addr, handle) = mock_server(responses, 1);
+        (QueryClient::new(addr).unwrap(), handle)
+    }
+
+    /// Spawn a mock query server like [`mock_client`], accepting `connections` subsequent connections.
+    ///
+    /// Received commands of all connections are returned in order.
+    pub(crate) fn mock_server(
+        responses: &[&str],
+        connections: usize,
+    ) -> (SocketAddr, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let responses: Vec<String> = responses.iter().rev().map(|v| v.to_string()).collect();
+        let responses = Arc::new(Mutex::new(responses));
+        let commands = Arc::new(Mutex::new(Vec::new()));
+        let handle = thread::spawn(move || {
+            // serve connections concurrently, a client may connect before closing the old one
+            let workers: Vec<_> = (0..connections)
+                .map(|_| {
+                    let (mut stream, _) = listener.accept().unwrap();
+                    let responses = responses.clone();
+                    let commands = commands.clone();
+                    thread::spawn(move || {
+                        stream
+                            .write_all(
+                                b"TS3\n\rWelcome to the TeamSpeak 3 ServerQuery interface.\n\r",
+                            )
+                            .unwrap();
+                        let mut reader = BufReader::new(stream.try_clone().unwrap());
+                        let mut line = String::new();
+                        while reader.read_line(&mut line).unwrap_or(0) != 0 {
+                            let command = line.trim_end().to_string();
+                            line.clear();
+                            let response = responses.lock().unwrap().pop().unwrap_or_else(|| {
+                                if command == "whoami" { WHOAMI } else { OK }.to_string()
+                            });
+                            commands.lock().unwrap().push(command);
+                            if stream.write_all(response.as_bytes()).is_err() {
+                                break;
+                            }
+                        }
+                    })
+                })
+                .collect();
+            for worker in workers {
+                worker.join().unwrap();
+            }
+            let commands = commands.lock().unwrap().clone();
+            commands
+        });
+        (addr, handle)
+    }
+
+    #[test]
+    fn test_raw_command_iter() {
+        let (mut client, handle) = mock_client(&[
+            "clid=1 cid=2|clid=3 cid=4\n\r\n\rclid=5 cid=6\n\rerror id=0 msg=ok\n\r",
+            "error id=1024 msg=invalid\\sserverID\n\r",
+        ]);
+        let lines = client
+            .raw_command_iter("clientlist")
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(vec!["clid=1 cid=2|clid=3 cid=4", "clid=5 cid=6"], lines);
+
+        let mut iter = client.raw_command_iter("use sid=9");
+        let err = iter.next().unwrap().unwrap_err();
+        assert_eq!(1024, err.error_response().unwrap().id);
+        assert!(iter.next().is_none());
+        drop(iter);
+
+        drop(client);
+        let commands = handle.join().unwrap();
+        assert_eq!(vec!["clientlist", "use sid=9", "quit"], commands);
+    }
+
+    #[test]
+    fn test_raw_command_iter_drop() {
+        let (mut client, handle) = mock_client(&[
+            "a=1\n\rb=2\n\rerror id=0 msg=ok\n\r",
+            "c=3\n\rerror id=0 msg=ok\n\r",
+        ]);
+        let mut iter = client.raw_command_iter("foo");
+        assert_eq!("a=1", iter.next().unwrap().unwrap());
+        drop(iter);
+        // remaining response was consumed
+        assert_eq!(vec!["c=3"], client.raw_command("bar").unwrap());
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_format_ids() {
+        let format = |ids: &[usize]| {
+            let mut out = String::from("cmd ");
+            QueryClient::format_ids("cldbid", ids, &mut out);
+            out
+        };
+        let ids = vec![0, 1, 2, 3];
+        assert_eq!("cmd cldbid=0|cldbid=1|cldbid=2|cldbid=3", format(&ids));
+        assert_eq!("cmd ", format(&[]));
+        assert_eq!("cmd cldbid=0", format(&ids[0..1]));
+        let mut out = String::new();
+        QueryClient::format_ids("clid", &[client_id(3), client_id(4)], &mut out);
+        assert_eq!("clid=3|clid=4", out);
+    }
+
+    #[test]
+    fn test_client_db_list() {
+        let (mut client, handle) = mock_client(&[
+            "cldbid=1 client_unique_identifier=abc= client_nickname=foo\\sbar client_created=1577836800 client_lastconnected=1577836900 client_totalconnections=3 client_description client_lastip=127.0.0.1 count=42|cldbid=2 client_unique_identifier=def= client_nickname=baz client_created=1577836800 client_lastconnected=1577836900 client_totalconnections=1 client_description=desc client_lastip\n\rerror id=0 msg=ok\n\r",
+        ]);
+        let (entries, count) = client.client_db_list(25, 2, true).unwrap();
+        assert_eq!(Some(42), count);
+        assert_eq!(2, entries.len());
+        assert_eq!(db_id(1), entries[0].cldbid);
+        assert_eq!("foo bar", entries[0].client_nickname);
+        assert_eq!(None, entries[0].client_description);
+        assert_eq!(Some("127.0.0.1"), entries[0].client_lastip.as_deref());
+        assert_eq!(db_id(2), entries[1].cldbid);
+        assert_eq!(Some("desc"), entries[1].client_description.as_deref());
+
+        let (entries, count) = client.client_db_list(0, 25, false).unwrap();
+        assert_eq!(None, count);
+        assert!(entries.is_empty());
+
+        drop(client);
+        let commands = handle.join().unwrap();
+        assert_eq!("clientdblist start=25 duration=2 -count", commands[0]);
+        assert_eq!("clientdblist start=0 duration=25", commands[1]);
+    }
+
+    #[test]
+    fn test_client_db_find() {
+        let (mut client, handle) =
+            mock_client(&["cldbid=2|cldbid=5|cldbid=42\n\rerror id=0 msg=ok\n\r"]);
+        let ids = client.client_db_find("foo bar", false).unwrap();
+        assert_eq!(vec![db_id(2), db_id(5), db_id(42)], ids);
+        client.client_db_find("abc/def=", true).unwrap();
+        client
+            .client_db_edit(
+                db_id(2),
+                &ClientDbEdit {
+                    client_description: Some("new description".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        client.client_db_delete(db_id(5)).unwrap();
+
+        drop(client);
+        let commands = handle.join().unwrap();
+        assert_eq!("clientdbfind pattern=foo\\sbar", commands[0]);
+        assert_eq!("clientdbfind pattern=abc\\/def= -uid", commands[1]);
+        assert_eq!(
+            "clientdbedit cldbid=2 client_description=new\\sdescription",
+            commands[2]
+        );
+        assert_eq!("clientdbdelete cldbid=5", commands[3]);
+    }
+
+    #[test]
+    fn test_permissions() {
+        let (mut client, handle) = mock_client(&[
+            "permsid=b_serverinstance_help_view permid=4353\n\rerror id=0 msg=ok\n\r",
+            "permid=4353 permname=b_serverinstance_help_view permdesc=Retrieve\\sinformation\\sabout\\sServerQuery\\scommands|permid=4354 permname=b_serverinstance_version_view permdesc\n\rerror id=0 msg=ok\n\r",
+        ]);
+        assert_eq!(
+            4353,
+            client
+                .perm_id_by_name("b_serverinstance_help_view")
+                .unwrap()
+        );
+        let perms = client.permission_list().unwrap();
+        assert_eq!(2, perms.len());
+        assert_eq!(4353, perms[0].permid);
+        assert_eq!("b_serverinstance_help_view", perms[0].permname);
+        assert_eq!(
+            Some("Retrieve information about ServerQuery commands"),
+            perms[0].permdesc.as_deref()
+        );
+        assert_eq!(4354, perms[1].permid);
+        assert_eq!(None, perms[1].permdesc);
+
+        drop(client);
+        let commands = handle.join().unwrap();
+        assert_eq!(
+            "permidgetbyname permsid=b_serverinstance_help_view",
+            commands[0]
+        );
+        assert_eq!("permissionlist", commands[1]);
+    }
+
+    #[test]
+    fn test_server_group_perm() {
+        let (mut client, handle) = mock_client(&[]);
+        client
+            .server_group_add_perm(group_id(7), 4353, 75, true, false)
+            .unwrap();
+        client.server_group_del_perm(group_id(7), 4353).unwrap();
+        client.channel_group_add_perm(5, 4353, 75).unwrap();
+        client.channel_add_perm(channel_id(3), 4353, 75).unwrap();
+        client.client_add_perm(db_id(2), 4353, 75, true).unwrap();
+
+        drop(client);
+        let commands = handle.join().unwrap();
+        assert_eq!(
+            "servergroupaddperm sgid=7 permid=4353 permvalue=75 permnegated=1 permskip=0",
+            commands[0]
+        );
+        assert_eq!("servergroupdelperm sgid=7 permid=4353", commands[1]);
+        assert_eq!(
+            "channelgroupaddperm cgid=5 permid=4353 permvalue=75",
+            commands[2]
+        );
+        assert_eq!("channeladdperm cid=3 permid=4353 permvalue=75", commands[3]);
+        assert_eq!(
+            "clientaddperm cldbid=2 permid=4353 permvalue=75 permskip=1",
+            commands[4]
+        );
+    }
+
+    #[test]
+    fn test_group_perm_list() {
+        let (mut client, handle) = mock_client(&[
+            "permid=8470 permvalue=1 permnegated=0 permskip=0|permid=8471 permvalue=75 permnegated=1 permskip=1\n\rerror id=0 msg=ok\n\r",
+            "permsid=b_channel_create_child permvalue=1 permnegated=0 permskip=0\n\rerror id=0 msg=ok\n\r",
+        ]);
+        let perms = client.server_group_perm_list(group_id(7), false).unwrap();
+        assert_eq!(2, perms.len());
+        assert!(matches!(perms[0].perm, PermIdent::Id(8470)));
+        assert_eq!(1, perms[0].permvalue);
+        assert!(!perms[0].permnegated);
+        assert!(matches!(perms[1].perm, PermIdent::Id(8471)));
+        assert_eq!(75, perms[1].permvalue);
+        assert!(perms[1].permnegated);
+        assert!(perms[1].permskip);
+
+        let perms = client.channel_group_perm_list(5, true).unwrap();
+        assert_eq!(1, perms.len());
+        match &perms[0].perm {
+            PermIdent::Name(name) => assert_eq!("b_channel_create_child", name),
+            v => panic!("expected permsid, got {:?}", v),
+        }
+
+        drop(client);
+        let commands = handle.join().unwrap();
+        assert_eq!("servergrouppermlist sgid=7", commands[0]);
+        assert_eq!("channelgrouppermlist cgid=5 -permsid", commands[1]);
+    }
+
+    #[test]
+    fn test_file_list() {
+        let (mut client, handle) = mock_client(&[
+            "cid=2 path=\\/ name=My\\sDirectory size=0 datetime=1577836800 type=0|name=file.txt size=1024 datetime=1577836900 type=1\n\rerror id=0 msg=ok\n\r",
+            "error id=2055 msg=no\\sfiles\\savailable\n\r",
+            "cid=2 name=\\/My\\sDirectory\\/file.txt size=1024 datetime=1577836900\n\rerror id=0 msg=ok\n\r",
+        ]);
+        let files = client.file_list(channel_id(2), "/", None).unwrap();
+        assert_eq!(2, files.len());
+        assert_eq!("My Directory", files[0].name);
+        assert_eq!(0, files[0].r#type);
+        assert_eq!("file.txt", files[1].name);
+        assert_eq!(1024, files[1].size);
+        assert_eq!(1577836900, files[1].datetime);
+        assert_eq!(1, files[1].r#type);
+
+        assert!(client
+            .file_list(channel_id(2), "/My Directory", Some("secret"))
+            .unwrap()
+            .is_empty());
+
+        let file = client
+            .file_info(channel_id(2), "/My Directory", "file.txt")
+            .unwrap();
+        assert_eq!("/My Directory/file.txt", file.name);
+        assert_eq!(1024, file.size);
+
+        drop(client);
+        let commands = handle.join().unwrap();
+        assert_eq!("ftgetfilelist cid=2 cpw= path=\\/", commands[0]);
+        assert_eq!(
+            "ftgetfilelist cid=2 cpw=secret path=\\/My\\sDirectory",
+            commands[1]
+        );
+        assert_eq!(
+            "ftgetfileinfo cid=2 cpw= name=\\/My\\sDirectory\\/file.txt",
+            commands[2]
+        );
+    }
+
+    #[test]
+    fn test_file_transfer_init() {
+        let (mut client, handle) = mock_client(&[
+            "clientftfid=1 serverftfid=6 ftkey=M7UukqaXmaMX3bgg port=30033 size=1024\n\rerror id=0 msg=ok\n\r",
+            "clientftfid=2 serverftfid=7 ftkey=qaXmaMX3bggM7Uuk port=30033 seekpos=512\n\rerror id=0 msg=ok\n\r",
+            "clientftfid=3 status=2051 msg=invalid\\sfile\\spath size=0\n\rerror id=0 msg=ok\n\r",
+        ]);
+        let dl = client
+            .file_init_download(channel_id(2), "/file.txt", None, 0)
+            .unwrap();
+        assert_eq!(1, dl.clientftfid);
+        assert_eq!(6, dl.serverftfid);
+        assert_eq!("M7UukqaXmaMX3bgg", dl.ftkey);
+        assert_eq!(30033, dl.port);
+        assert_eq!(Some(1024), dl.size);
+        assert_eq!(None, dl.seekpos);
+
+        let ul = client
+            .file_init_upload(channel_id(2), "/my file.txt", 2048, false, true)
+            .unwrap();
+        assert_eq!(2, ul.clientftfid);
+        assert_eq!(Some(512), ul.seekpos);
+        assert_eq!(None, ul.size);
+
+        let err = client
+            .file_init_download(channel_id(2), "/missing.txt", Some("pw"), 0)
+            .unwrap_err();
+        assert_eq!(2051, err.error_response().unwrap().id);
+
+        drop(client);
+        let commands = handle.join().unwrap();
+        assert_eq!(
+            "ftinitdownload clientftfid=1 name=\\/file.txt cid=2 cpw= seekpos=0",
+            commands[0]
+        );
+        assert_eq!(
+            "ftinitupload clientftfid=2 name=\\/my\\sfile.txt cid=2 cpw= size=2048 overwrite=0 resume=1",
+            commands[1]
+        );
+        assert_eq!(
+            "ftinitdownload clientftfid=3 name=\\/missing.txt cid=2 cpw=pw seekpos=0",
+            commands[2]
+        );
+    }
+
+    #[test]
+    fn test_log() {
+        let (mut client, handle) = mock_client(&[
+            "last_pos=403788 file_size=411980 l=2020-01-01\\s12:00:00.000000\\pINFO\\s\\s\\s\\s\\pServerLibPriv\\s\\p\\s\\s\\s\\pServer\\sstarted|l=2020-01-01\\s12:00:01.000000\\pINFO\\s\\s\\s\\s\\pVirtualServer\\s\\p1\\s\\s\\plistening\\son\\s0.0.0.0:9987\n\rerror id=0 msg=ok\n\r",
+        ]);
+        let log = client.log_view(2, true, false, Some(411980)).unwrap();
+        assert_eq!(403788, log.last_pos);
+        assert_eq!(411980, log.file_size);
+        assert_eq!(
+            vec![
+                "2020-01-01 12:00:00.000000|INFO    |ServerLibPriv |   |Server started",
+                "2020-01-01 12:00:01.000000|INFO    |VirtualServer |1  |listening on 0.0.0.0:9987"
+            ],
+            log.lines
+        );
+        client.log_view(100, false, true, None).unwrap();
+        client.log_add(4, "bot started").unwrap();
+
+        drop(client);
+        let commands = handle.join().unwrap();
+        assert_eq!(
+            "logview lines=2 reverse=1 instance=0 begin_pos=411980",
+            commands[0]
+        );
+        assert_eq!("logview lines=100 reverse=0 instance=1", commands[1]);
+        assert_eq!("logadd loglevel=4 logmsg=bot\\sstarted", commands[2]);
+    }
+
+    #[test]
+    fn test_log_empty() {
+        let (mut client, handle) =
+            mock_client(&["last_pos=0 file_size=0\n\rerror id=0 msg=ok\n\r"]);
+        let log = client.log_view(100, false, false, None).unwrap();
+        assert_eq!(0, log.last_pos);
+        assert_eq!(0, log.file_size);
+        assert!(log.lines.is_empty());
+
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_binding_list() {
+        let (mut client, handle) = mock_client(&["ip=0.0.0.0|ip=::\n\rerror id=0 msg=ok\n\r"]);
+        assert_eq!(
+            vec!["0.0.0.0", "::"],
+            client.binding_list(Some("query")).unwrap()
+        );
+        client.binding_list(None).unwrap();
+
+        drop(client);
+        let commands = handle.join().unwrap();
+        assert_eq!("bindinglist subsystem=query", commands[0]);
+        assert_eq!("bindinglist", commands[1]);
+    }
+
+    #[test]
+    fn test_connection_info() {
+        let (mut client, handle) = mock_client(&[
+            "connection_filetransfer_bandwidth_sent=0 connection_filetransfer_bandwidth_received=0 connection_filetransfer_bytes_sent_total=617 connection_filetransfer_bytes_received_total=0 connection_packets_sent_total=926413 connection_bytes_sent_total=92911395 connection_packets_received_total=650335 connection_bytes_received_total=61940731 connection_bandwidth_sent_last_second_total=0 connection_bandwidth_sent_last_minute_total=0 connection_bandwidth_received_last_second_total=0 connection_bandwidth_received_last_minute_total=0 connection_connected_time=49408 connection_packetloss_total=0.0125 connection_ping=12.5\n\rerror id=0 msg=ok\n\r",
+            "cid=2 client_idle_time=1280 client_nickname=foo connection_filetransfer_bandwidth_sent=0 connection_filetransfer_bandwidth_received=0 connection_packets_sent_total=1042 connection_bytes_sent_total=123456 connection_packets_received_total=1000 connection_bytes_received_total=654321 connection_bandwidth_sent_last_second_total=81 connection_bandwidth_sent_last_minute_total=92 connection_bandwidth_received_last_second_total=83 connection_bandwidth_received_last_minute_total=88 connection_connected_time=60000 connection_client_ip=127.0.0.1\n\rerror id=0 msg=ok\n\r",
+        ]);
+        let info = client.connection_info().unwrap();
+        assert_eq!(92911395, info.connection_bytes_sent_total);
+        assert_eq!(49408, info.connection_connected_time);
+        assert!((info.connection_packetloss_total - 0.0125).abs() < f64::EPSILON);
+        assert!((info.connection_ping - 12.5).abs() < f64::EPSILON);
+
+        let info = client.client_connection_info(client_id(3)).unwrap();
+        assert_eq!(123456, info.connection_bytes_sent_total);
+        assert_eq!(81, info.connection_bandwidth_sent_last_second_total);
+        assert_eq!(Some("127.0.0.1"), info.connection_client_ip.as_deref());
+        assert_eq!(None, info.connection_ping);
+        assert_eq!(None, info.connection_packetloss_total);
+
+        drop(client);
+        let commands = handle.join().unwrap();
+        assert_eq!("serverrequestconnectioninfo", commands[0]);
+        assert_eq!("clientinfo clid=3", commands[1]);
+    }
+
+    #[test]
+    fn test_api_keys() {
+        let (mut client, handle) = mock_client(&[
+            "apikey=BAByFoiEXZfnSJyE6dbXFiW_nn_SdwkclpKNz9j id=3 sid=1 cldbid=1 scope=manage time_left=unlimited created_at=1577836800 expires_at=0\n\rerror id=0 msg=ok\n\r",
+            "id=3 cldbid=1 sid=1 scope=manage created_at=1577836800 expires_at=0|id=4 cldbid=2 sid=1 scope=read created_at=1577836800 expires_at=1578700800\n\rerror id=0 msg=ok\n\r",
+            "apikey=Zk8x2Ls_Q1vbMnoPq7Rt3UwXy9aBcDeFgHiJkLm id=5 sid=1 cldbid=2 scope=read time_left=604800 created_at=1577836800 expires_at=1578441600\n\rerror id=0 msg=ok\n\r",
+        ]);
+        assert_eq!(
+            "BAByFoiEXZfnSJyE6dbXFiW_nn_SdwkclpKNz9j",
+            client.api_key_add(ApiScope::Manage, None, None).unwrap()
+        );
+        let keys = client.api_key_list(None).unwrap();
+        assert_eq!(2, keys.len());
+        assert_eq!(3, keys[0].id);
+        assert_eq!(ApiScope::Manage, keys[0].scope);
+        assert_eq!(None, keys[0].expires_at);
+        assert_eq!(db_id(2), keys[1].cldbid);
+        assert_eq!(ApiScope::Read, keys[1].scope);
+        assert_eq!(Some(1578700800), keys[1].expires_at);
+        assert_eq!(
+            "Zk8x2Ls_Q1vbMnoPq7Rt3UwXy9aBcDeFgHiJkLm",
+            client
+                .api_key_add(ApiScope::Read, Some(7), Some(db_id(2)))
+                .unwrap()
+        );
+        client.api_key_del(3).unwrap();
+
+        drop(client);
+        let commands = handle.join().unwrap();
+        assert_eq!("apikeyadd scope=manage lifetime=0", commands[0]);
+        assert_eq!("apikeylist cldbid=*", commands[1]);
+        assert_eq!("apikeyadd scope=read lifetime=7 cldbid=2", commands[2]);
+        assert_eq!("apikeydel id=3", commands[3]);
+    }
+
+    #[test]
+    fn test_api_key_add_missing_key() {
+        let (mut client, handle) = mock_client(&[OK]);
+        match client.api_key_add(ApiScope::Read, None, None) {
+            Err(Ts3Error::NoEntryResponse { key, .. }) => assert_eq!("apikey", key),
+            v => panic!("expected NoEntryResponse, got {:?}", v),
+        }
+
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let snapshot = format!("version=2 salt=abc data={}", "a".repeat(70_000));
+        let (mut client, handle) = mock_client(&[&format!("{}\n\r{}", snapshot, OK)]);
+        assert_eq!(snapshot, client.snapshot_create().unwrap());
+        assert_eq!(LIMIT_LINE_BYTES, client.limit_lines_bytes);
+        client.snapshot_deploy(&snapshot).unwrap();
+
+        drop(client);
+        let commands = handle.join().unwrap();
+        assert_eq!("serversnapshotcreate", commands[0]);
+        assert_eq!(format!("serversnapshotdeploy {}", snapshot), commands[1]);
+    }
+
+    #[test]
+    fn test_set_away() {
+        let (mut client, handle) = mock_client(&[]);
+        client.set_away(true, Some("be right back")).unwrap();
+        client.set_away(false, Some("ignored")).unwrap();
+
+        drop(client);
+        let commands = handle.join().unwrap();
+        assert_eq!(
+            "clientupdate client_away=1 client_away_message=be\\sright\\sback",
+            commands[0]
+        );
+        assert_eq!(
+            "clientupdate client_away=0 client_away_message=",
+            commands[1]
+        );
+    }
+
+    #[test]
+    fn test_client_update() {
+        let update = ClientUpdate {
+            client_nickname: Some("my bot".to_string()),
+            client_away: Some(true),
+            client_away_message: Some("afk".to_string()),
+            client_input_muted: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(
+            " client_nickname=my\\sbot client_away=1 client_away_message=afk client_input_muted=0",
+            update.to_raw()
+        );
+
+        let (mut client, handle) = mock_client(&[]);
+        client.client_update(&update).unwrap();
+        client.rename("foo bar").unwrap();
+        client.update_description("some description", None).unwrap();
+        client.set_channel_commander(true).unwrap();
+        client.set_recording_indicator(false).unwrap();
+
+        drop(client);
+        let commands = handle.join().unwrap();
+        assert_eq!(format!("clientupdate{}", update.to_raw()), commands[0]);
+        assert_eq!("clientupdate client_nickname=foo\\sbar", commands[1]);
+        assert_eq!(
+            "clientupdate client_description=some\\sdescription",
+            commands[2]
+        );
+        assert_eq!("clientupdate client_is_channel_commander=1", commands[3]);
+        assert_eq!("clientupdate client_is_recording=0", commands[4]);
+    }
+
+    #[test]
+    fn test_close() {
+        let (mut client, handle) = mock_client(&[]);
+        client.close().unwrap();
+        // no double close
+        client.close().unwrap();
+        drop(client);
+        // no logout without login
+        assert_eq!(vec!["quit"], handle.join().unwrap());
+
+        let (mut client, handle) = mock_client(&[]);
+        client.login("serveradmin", "pass").unwrap();
+        client.close().unwrap();
+        drop(client);
+        assert_eq!(
+            vec!["login serveradmin pass", "logout", "quit"],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_close_logout_error() {
+        let (mut client, handle) = mock_client(&[
+            OK,
+            "error id=2568 msg=insufficient\\sclient\\spermissions\n\r",
+        ]);
+        client.login("serveradmin", "pass").unwrap();
+        let err = client.close().unwrap_err();
+        assert!(err.error_response().unwrap().is_permission_denied());
+        drop(client);
+        // quit is still sent
+        assert_eq!(
+            vec!["login serveradmin pass", "logout", "quit"],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_reconnect_like() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let streams: Vec<TcpStream> = (0..2)
+                .map(|_| {
+                    let (mut stream, _) = listener.accept().unwrap();
+                    stream.write_all(b"TS3\n\rWelcome\n\r").unwrap();
+                    stream
+                })
+                .collect();
+            // keep open until the clients quit
+            for mut stream in streams {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                assert_eq!("quit", line.trim_end());
+                stream.write_all(OK.as_bytes()).unwrap();
+            }
+        });
+        let timeout = Some(Duration::from_secs(5));
+        let mut client = QueryClient::with_timeout(addr, timeout, timeout).unwrap();
+        client.limit_lines(42);
+        client.limit_line_bytes(1337);
+
+        let second = client.reconnect_like().unwrap();
+        assert_eq!(42, second.limit_lines);
+        assert_eq!(1337, second.limit_lines_bytes);
+        assert_eq!(Some(addr), second.addr);
+        assert_eq!(timeout, second.timeout);
+        assert_eq!(timeout, second.conn_timeout);
+        assert_eq!(
+            timeout,
+            second.tcp_stream().unwrap().read_timeout().unwrap()
+        );
+        drop(client);
+        drop(second);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_reconnect() {
+        let (addr, handle) = mock_server(&[], 2);
+        let mut client = QueryClient::new(addr).unwrap();
+        client.login("serveradmin", "pass word").unwrap();
+        client.select_server_by_port(9987).unwrap();
+        client.reconnect().unwrap();
+        drop(client);
+
+        let commands = handle.join().unwrap();
+        assert_eq!(
+            vec![
+                "login serveradmin pass\\sword",
+                "use port=9987",
+                "login serveradmin pass\\sword",
+                "use port=9987",
+                "quit"
+            ],
+            commands
+        );
+    }
+
+    #[test]
+    fn test_with_retry() {
+        let (addr, handle) = mock_server(
+            &[
+                OK,
+                OK,
+                "virtualserver_id=1\n\rerror id=0 msg=ok\n\r",
+                "error id=1024 msg=invalid\\sserverID\n\r",
+            ],
+            2,
+        );
+        let mut client = QueryClient::new(addr).unwrap();
+        client.select_server_by_id(1).unwrap();
+        let mut calls = 0;
+        let res = client
+            .with_retry(3, |c| {
+                calls += 1;
+                if calls == 1 {
+                    return ConnectionClosed {}.fail();
+                }
+                c.raw_command("whoami")
+            })
+            .unwrap();
+        assert_eq!(2, calls);
+        assert_eq!(vec!["virtualserver_id=1"], res);
+
+        // server errors are not retried
+        let mut calls = 0;
+        let err = client
+            .with_retry(3, |c| {
+                calls += 1;
+                c.select_server_by_id(2)
+            })
+            .unwrap_err();
+        assert_eq!(1, calls);
+        assert!(err.is_error_response());
+        drop(client);
+
+        let commands = handle.join().unwrap();
+        assert_eq!(
+            vec!["use sid=1", "use sid=1", "whoami", "use sid=2", "quit"],
+            commands
+        );
+    }
+
+    #[test]
+    fn test_timeout() {
+        let (addr, handle) = mock_server(&[""], 1);
+        let mut client =
+            QueryClient::with_timeout(addr, None, Some(Duration::from_millis(100))).unwrap();
+        let err = client.raw_command("whoami").unwrap_err();
+        assert!(err.is_timeout());
+        assert!(err.is_connection_error());
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_select_server_by_port_with_name() {
+        let (mut client, handle) = mock_client(&[]);
+        client
+            .select_server_by_port_with_name(9987, "my bot|1")
+            .unwrap();
+        drop(client);
+        assert_eq!(
+            vec!["use port=9987 client_nickname=my\\sbot\\p1", "quit"],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_client_find() {
+        let (mut client, handle) = mock_client(&[
+            "clid=3 client_nickname=Some\\sBot|clid=7 client_nickname=Bot\\p2\n\rerror id=0 msg=ok\n\r",
+            "error id=512 msg=invalid\\sclientID\n\r",
+        ]);
+        assert_eq!(
+            vec![
+                (client_id(3), "Some Bot".to_string()),
+                (client_id(7), "Bot|2".to_string())
+            ],
+            client.client_find("Bot").unwrap()
+        );
+        assert!(client.client_find("no match").unwrap().is_empty());
+        drop(client);
+        assert_eq!(
+            vec![
+                "clientfind pattern=Bot",
+                "clientfind pattern=no\\smatch",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_channel_find_info() {
+        let (mut client, handle) = mock_client(&[
+            "cid=1 channel_name=Default\\sChannel|cid=4 channel_name=AFK\\sChannel\n\rerror id=0 msg=ok\n\r",
+            "error id=768 msg=invalid\\schannelID\n\r",
+            "pid=1 channel_name=AFK\\sChannel channel_topic channel_description channel_codec=4 channel_codec_quality=6 channel_maxclients=-1 channel_maxfamilyclients=-1 channel_order=1 channel_flag_permanent=1 channel_flag_semi_permanent=0 channel_flag_default=0 channel_flag_password=0 channel_needed_talk_power=0 channel_icon_id=0 seconds_empty=42\n\rerror id=0 msg=ok\n\r",
+        ]);
+        assert_eq!(
+            vec![
+                (channel_id(1), "Default Channel".to_string()),
+                (channel_id(4), "AFK Channel".to_string())
+            ],
+            client.channel_find("Channel").unwrap()
+        );
+        assert!(client.channel_find("none").unwrap().is_empty());
+
+        let channel = client.channel_info(channel_id(4)).unwrap();
+        // injected from the request
+        assert_eq!(channel_id(4), channel.cid);
+        assert_eq!(0, channel.total_clients);
+        // taken from the response
+        assert_eq!(channel_id(1), channel.pid);
+        assert_eq!("AFK Channel", channel.channel_name);
+        assert_eq!(None, channel.channel_topic);
+        assert!(channel.channel_flag_permanent);
+        assert_eq!(42, channel.seconds_empty);
+
+        drop(client);
+        assert_eq!(
+            vec![
+                "channelfind pattern=Channel",
+                "channelfind pattern=none",
+                "channelinfo cid=4",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_move_channel() {
+        let (mut client, handle) =
+            mock_client(&[OK, OK, "error id=776 msg=invalid\\spermanent\\schannel\n\r"]);
+        client
+            .move_channel(channel_id(5), channel_id(2), None)
+            .unwrap();
+        client
+            .move_channel(channel_id(5), channel_id(2), Some(channel_id(3)))
+            .unwrap();
+        let err = client
+            .move_channel(channel_id(6), channel_id(7), None)
+            .unwrap_err();
+        assert_eq!(776, err.error_response().unwrap().id);
+        drop(client);
+        assert_eq!(
+            vec![
+                "channelmove cid=5 cpid=2",
+                "channelmove cid=5 cpid=2 order=3",
+                "channelmove cid=6 cpid=7",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_set_channel_life() {
+        let (mut client, handle) = mock_client(&[]);
+        client
+            .set_channel_life(channel_id(3), ChannelLife::Permanent)
+            .unwrap();
+        client
+            .set_channel_life(channel_id(3), ChannelLife::SemiPermanent)
+            .unwrap();
+        client
+            .set_channel_life(channel_id(3), ChannelLife::Temporary)
+            .unwrap();
+        drop(client);
+        assert_eq!(
+            vec![
+                "channeledit cid=3 channel_flag_permanent=1",
+                "channeledit cid=3 channel_flag_semi_permanent=1",
+                "channeledit cid=3 channel_flag_temporary=1",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_message_wrappers() {
+        let (mut client, handle) = mock_client(&[]);
+        client.message_client(client_id(4), "hello there").unwrap();
+        client.message_channel("hello channel").unwrap();
+        client.message_server("hello server").unwrap();
+        drop(client);
+        assert_eq!(
+            vec![
+                "sendtextmessage targetmode=1 target=4 msg=hello\\sthere",
+                "sendtextmessage targetmode=2 msg=hello\\schannel",
+                "sendtextmessage targetmode=3 msg=hello\\sserver",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_message_to_channel() {
+        let (mut client, handle) = mock_client(&[
+            "virtualserver_status=online virtualserver_id=1 client_id=5 client_channel_id=1\n\rerror id=0 msg=ok\n\r",
+            OK,
+            OK,
+            "error id=770 msg=already\\smember\\sof\\schannel\n\r",
+        ]);
+        client.message_to_channel(channel_id(3), "hello").unwrap();
+        client.message_to_channel(channel_id(3), "again").unwrap();
+        drop(client);
+        assert_eq!(
+            vec![
+                "whoami",
+                "clientmove clid=5 cid=3",
+                "sendtextmessage targetmode=2 msg=hello",
+                "clientmove clid=5 cid=3",
+                "sendtextmessage targetmode=2 msg=again",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_set_client_channel_group_bulk() {
+        let (mut client, handle) =
+            mock_client(&[OK, OK, "error id=512 msg=invalid\\sclientID\n\r"]);
+        assert_eq!(
+            2,
+            client
+                .set_client_channel_group_bulk(
+                    8,
+                    channel_id(3),
+                    &[db_id(10), db_id(11), db_id(12), db_id(13)]
+                )
+                .unwrap()
+        );
+        assert_eq!(
+            1,
+            client
+                .set_client_channel_group_bulk(8, channel_id(3), &[db_id(14)])
+                .unwrap()
+        );
+        drop(client);
+        assert_eq!(
+            vec![
+                "setclientchannelgroup cgid=8 cid=3 cldbid=10",
+                "setclientchannelgroup cgid=8 cid=3 cldbid=11",
+                "setclientchannelgroup cgid=8 cid=3 cldbid=12",
+                "setclientchannelgroup cgid=8 cid=3 cldbid=14",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_perm_overview() {
+        let (mut client, handle) = mock_client(&[
+            "cid=3 cldbid=7 permid=0 t=0 id1=6 id2=0 p=4353 v=75 n=0 s=1|t=4 id1=3 id2=7 p=4354 v=-1 n=1 s=0\n\rerror id=0 msg=ok\n\r",
+        ]);
+        let perms = client.perm_overview(channel_id(3), db_id(7)).unwrap();
+        assert_eq!(2, perms.len());
+        assert_eq!(0, perms[0].t);
+        assert_eq!(6, perms[0].id1);
+        assert_eq!(4353, perms[0].permid);
+        assert_eq!(75, perms[0].value);
+        assert!(!perms[0].negated);
+        assert!(perms[0].skip);
+        assert_eq!(4, perms[1].t);
+        assert_eq!(3, perms[1].id1);
+        assert_eq!(7, perms[1].id2);
+        assert_eq!(-1, perms[1].value);
+        assert!(perms[1].negated);
+        drop(client);
+        assert_eq!(
+            vec!["permoverview cid=3 cldbid=7 permid=0", "quit"],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_empty_result() {
+        let empty = "error id=1281 msg=database\\sempty\\sresult\\sset\n\r";
+        let (mut client, handle) = mock_client(&[empty, empty, empty]);
+        assert_eq!(Vec::<ServerGroup>::new(), client.server_groups().unwrap());
+        assert!(client
+            .servergroup_client_cldbids(group_id(7))
+            .unwrap()
+            .is_empty());
+        let err = client.raw_command("servergrouplist").unwrap_err();
+        assert!(err.is_empty_result());
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_with_line_byte_limit() {
+        let (mut client, handle) = mock_client(&[OK, "error id=1024 msg=invalid\\sserverID\n\r"]);
+        client.limit_line_bytes(100);
+        let limit = client
+            .with_line_byte_limit(5000, |c| {
+                c.ping()?;
+                Ok(c.limit_lines_bytes)
+            })
+            .unwrap();
+        assert_eq!(5000, limit);
+        assert_eq!(100, client.limit_lines_bytes);
+
+        let err = client
+            .with_line_byte_limit(5000, |c| c.select_server_by_id(2))
+            .unwrap_err();
+        assert!(err.is_error_response());
+        assert_eq!(100, client.limit_lines_bytes);
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_line_byte_limit_all_lines() {
+        let long = "a".repeat(LIMIT_LINE_BYTES as usize + 10);
+        let response = format!("first\n\r{}\n\r{}\n\rerror id=0 msg=ok\n\r", long, long);
+        let (mut client, handle) = mock_client(&[&response]);
+        client.limit_line_bytes(LIMIT_LINE_BYTES * 2);
+        let res = client.raw_command("serversnapshotcreate").unwrap();
+        assert_eq!(vec!["first".to_string(), long.clone(), long], res);
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_greeting_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            // no welcome message, client has to wait for the greeting timeout
+            stream.write_all(b"TS3\n\r").unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap_or(0) != 0 {
+                // respond slower than the greeting timeout
+                thread::sleep(Duration::from_millis(200));
+                stream.write_all(OK.as_bytes()).unwrap();
+                line.clear();
+            }
+        });
+        let mut client = QueryClient::with_greeting_timeout(
+            addr,
+            None,
+            Some(Duration::from_secs(5)),
+            Some(Duration::from_millis(50)),
+        )
+        .unwrap();
+        client.ping().unwrap();
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_not_a_query_port() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream
+                .write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n")
+                .unwrap();
+        });
+        match QueryClient::new(addr) {
+            Err(Ts3Error::NotAQueryPort { got, .. }) => assert_eq!("HTTP/1.1 400 Bad Request", got),
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("expected error"),
+        }
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_kick() {
+        let (mut client, handle) = mock_client(&[]);
+        client
+            .kick_from_channel(client_id(3), Some("go away"))
+            .unwrap();
+        client.kick_from_server(client_id(3), None).unwrap();
+        client.kick_client(client_id(4), true, Some("bye")).unwrap();
+        client.kick_client(client_id(4), false, None).unwrap();
+        drop(client);
+        assert_eq!(
+            vec![
+                "clientkick clid=3 reasonid=4 reasonmsg=go\\saway",
+                "clientkick clid=3 reasonid=5",
+                "clientkick clid=4 reasonid=5 reasonmsg=bye",
+                "clientkick clid=4 reasonid=4",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_raw_command_expect_empty() {
+        let (mut client, handle) = mock_client(&[OK, "cid=1|cid=2\n\rerror id=0 msg=ok\n\r"]);
+        client
+            .raw_command_expect_empty("clientmove clid=1 cid=2")
+            .unwrap();
+        match client.raw_command_expect_empty("channellist") {
+            Err(Ts3Error::InvalidResponse { data, .. }) => assert_eq!("cid=1|cid=2", data),
+            v => panic!("expected invalid response, got {:?}", v),
+        }
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_get_field() {
+        let (mut client, handle) = mock_client(&[
+            "cluid=abc cldbid=42\n\rerror id=0 msg=ok\n\r",
+            "cluid=abc cldbid=foo\n\rerror id=0 msg=ok\n\r",
+            "cluid=abc\\/def\n\rerror id=0 msg=ok\n\r",
+        ]);
+        let cldbid: ClientDBId = client
+            .get_field("clientgetdbidfromuid cluid=abc", "cldbid")
+            .unwrap();
+        assert_eq!(db_id(42), cldbid);
+        assert!(client
+            .get_field::<ClientDBId>("clientgetdbidfromuid cluid=abc", "cldbid")
+            .is_err());
+        let uid: String = client
+            .get_field("clientgetuidfromclid clid=1", "cluid")
+            .unwrap();
+        assert_eq!("abc/def", uid);
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_ping_latency() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(b"TS3\n\rWelcome\n\r").unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap_or(0) != 0 {
+                if line.trim_end() == "whoami" {
+                    thread::sleep(Duration::from_millis(100));
+                }
+                stream.write_all(OK.as_bytes()).unwrap();
+                line.clear();
+            }
+        });
+        let mut client = QueryClient::new(addr).unwrap();
+        let latency = client.ping_latency().unwrap();
+        assert!(latency >= Duration::from_millis(100));
+        assert!(latency < Duration::from_secs(5));
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_peer_addr() {
+        let (addr, handle) = mock_server(&[], 2);
+        let mut client = QueryClient::new(addr).unwrap();
+        assert_eq!(addr, client.peer_addr().unwrap());
+        assert_eq!(Some(addr), client.addr());
+        assert_ne!(addr, client.local_addr().unwrap());
+        client.reconnect().unwrap();
+        assert_eq!(addr, client.peer_addr().unwrap());
+        assert_eq!(Some(addr), client.addr());
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_set_timeout() {
+        let (mut client, handle) = mock_client(&[]);
+        let read = Some(Duration::from_secs(3));
+        let write = Some(Duration::from_secs(4));
+        client.set_timeout(read, write).unwrap();
+        assert_eq!(read, client.tcp_stream().unwrap().read_timeout().unwrap());
+        assert_eq!(write, client.tcp_stream().unwrap().write_timeout().unwrap());
+        client.set_timeout(None, None).unwrap();
+        assert_eq!(None, client.tcp_stream().unwrap().read_timeout().unwrap());
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_selected_server() {
+        let (mut client, handle) = mock_client(&[
+            "virtualserver_status=online virtualserver_id=3 client_id=5\n\rerror id=0 msg=ok\n\r",
+            OK,
+            "virtualserver_status=online virtualserver_id=4 client_id=5\n\rerror id=0 msg=ok\n\r",
+        ]);
+        assert_eq!(3, client.selected_server().unwrap());
+        assert_eq!(3, client.selected_server().unwrap());
+        client.select_server_by_id(4).unwrap();
+        assert_eq!(4, client.selected_server().unwrap());
+        drop(client);
+        assert_eq!(
+            vec!["whoami", "use sid=4", "whoami", "quit"],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_instance_edit() {
+        let (mut client, handle) = mock_client(&[]);
+        client
+            .instance_edit(&[
+                ("serverinstance_filetransfer_port", "30033".to_string()),
+                ("serverinstance_some_name", "a b|c".to_string()),
+            ])
+            .unwrap();
+        let edit = InstanceEdit {
+            serverinstance_guest_serverquery_group: Some(group_id(1)),
+            serverinstance_serverquery_flood_commands: Some(50),
+            ..Default::default()
+        };
+        client.instance_edit(&edit.props()).unwrap();
+        drop(client);
+        assert_eq!(
+            vec![
+                "instanceedit serverinstance_filetransfer_port=30033 serverinstance_some_name=a\\sb\\pc",
+                "instanceedit serverinstance_guest_serverquery_group=1 serverinstance_serverquery_flood_commands=50",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "dangerous-instance-control")]
+    fn test_instance_stop() {
+        let (mut client, handle) = mock_client(&[]);
+        client.instance_stop(None).unwrap();
+        client.instance_stop(Some("update time")).unwrap();
+        client.instance_restart(None).unwrap();
+        client.instance_restart(Some("update time")).unwrap();
+        drop(client);
+        assert_eq!(
+            vec![
+                "serverprocessstop",
+                "serverprocessstop reasonmsg=update\\stime",
+                "serverprocessrestart",
+                "serverprocessrestart reasonmsg=update\\stime",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "dangerous-instance-control")]
+    fn test_permission_reset() {
+        let (mut client, handle) =
+            mock_client(&["token=MqQbPLLm6jLC+x8j\\/\\sB1TrQpD\n\rerror id=0 msg=ok\n\r"]);
+        assert_eq!(
+            "MqQbPLLm6jLC+x8j/ B1TrQpD",
+            client.permission_reset().unwrap()
+        );
+        drop(client);
+        assert_eq!(vec!["permreset", "quit"], handle.join().unwrap());
+    }
+
+    #[test]
+    fn test_privilege_keys() {
+        let list = "token=abc\\/def token_type=0 token_id1=6 token_id2=0 token_created=1577836800 token_description=admin\\skey\n\rerror id=0 msg=ok\n\r";
+        let add = "token=abc\\/def\n\rerror id=0 msg=ok\n\r";
+        let (mut client, handle) = mock_client(&[add, list, OK, OK, add, list, OK, add]);
+        for legacy in &[false, true] {
+            client.legacy_tokens(*legacy);
+            let token = client
+                .privilege_key_add(
+                    PrivilegeKeyTarget::ServerGroup(group_id(6)),
+                    Some("admin key"),
+                )
+                .unwrap();
+            assert_eq!("abc/def", token);
+            let keys = client.privilege_key_list().unwrap();
+            assert_eq!(1, keys.len());
+            assert_eq!("abc/def", keys[0].token);
+            assert_eq!(6, keys[0].token_id1);
+            assert_eq!(Some("admin key".to_string()), keys[0].token_description);
+            client.privilege_key_delete(&token).unwrap();
+            if !legacy {
+                client.privilege_key_use(&token).unwrap();
+            }
+        }
+        client
+            .privilege_key_add(PrivilegeKeyTarget::ChannelGroup(5, channel_id(2)), None)
+            .unwrap();
+        client.privilege_key_use("abc/def").unwrap();
+        drop(client);
+        assert_eq!(
+            vec![
+                "privilegekeyadd tokentype=0 tokenid1=6 tokenid2=0 tokendescription=admin\\skey",
+                "privilegekeylist",
+                "privilegekeydelete token=abc\\/def",
+                "privilegekeyuse token=abc\\/def",
+                "tokenadd tokentype=0 tokenid1=6 tokenid2=0 tokendescription=admin\\skey",
+                "tokenlist",
+                "tokendelete token=abc\\/def",
+                "tokenadd tokentype=1 tokenid1=5 tokenid2=2",
+                "tokenuse token=abc\\/def",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_client_edit() {
+        let (mut client, handle) = mock_client(&[]);
+        client
+            .client_edit(
+                client_id(4),
+                &[
+                    ("client_is_talker", "1".to_string()),
+                    ("client_description", "talk power granted".to_string()),
+                ],
+            )
+            .unwrap();
+        client
+            .update_description("a b", Some(client_id(4)))
+            .unwrap();
+        drop(client);
+        assert_eq!(
+            vec![
+                "clientedit clid=4 client_is_talker=1 client_description=talk\\spower\\sgranted",
+                "clientedit clid=4 CLIENT_DESCRIPTION=a\\sb",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_servergroup_client_list_named() {
+        let (mut client, handle) = mock_client(&[
+            "cldbid=2 client_nickname=foo\\sbar client_unique_identifier=abc\\/d=|cldbid=5 client_nickname=baz client_unique_identifier=efg=\n\rerror id=0 msg=ok\n\r",
+        ]);
+        assert_eq!(
+            vec![
+                (db_id(2), "foo bar".to_string(), "abc/d=".to_string()),
+                (db_id(5), "baz".to_string(), "efg=".to_string())
+            ],
+            client.servergroup_client_list_named(group_id(7)).unwrap()
+        );
+        drop(client);
+        assert_eq!(
+            vec!["servergroupclientlist sgid=7 -names", "quit"],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_get_servergroup_client_list() {
+        let (mut client, handle) =
+            mock_client(&["cldbid=2|cldbid=5|cldbid=42\n\rerror id=0 msg=ok\n\r"]);
+        assert_eq!(
+            vec![db_id(2), db_id(5), db_id(42)],
+            client.get_servergroup_client_list(group_id(7)).unwrap()
+        );
+        drop(client);
+        assert_eq!(
+            vec!["servergroupclientlist sgid=7", "quit"],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_from_streams() {
+        use std::io::Cursor;
+        use std::sync::{Arc, Mutex};
+
+        /// Writer shared with the test to check the commands
+        #[derive(Clone, Default)]
+        struct Shared(Arc<Mutex<Vec<u8>>>);
+        impl Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let script = "TS3\n\rWelcome\n\rvirtualserver_status=online virtualserver_id=1 client_id=3\n\rerror id=0 msg=ok\n\r";
+        let writer = Shared::default();
+        let mut client = QueryClient::from_streams(Cursor::new(script), writer.clone()).unwrap();
+        assert_eq!(1, client.selected_server().unwrap());
+        assert!(client.peer_addr().is_err());
+        assert!(client.reconnect().is_err());
+        // script is exhausted
+        assert!(client.ping().unwrap_err().is_connection_error());
+        drop(client);
+        assert_eq!(
+            "whoami\nwhoami\nquit\n",
+            String::from_utf8(writer.0.lock().unwrap().clone()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_online_users() {
+        let (mut client, handle) = mock_client(&[
+            "clid=1 cid=1 client_database_id=1 client_nickname=serveradmin client_type=1|clid=2 cid=1 client_database_id=5 client_nickname=foo client_type=0|clid=3 cid=2 client_database_id=6 client_nickname=bar client_type=0\n\rerror id=0 msg=ok\n\r",
+        ]);
+        let users = client.online_users().unwrap();
+        assert_eq!(
+            vec!["foo", "bar"],
+            users
+                .iter()
+                .map(|c| c.client_nickname.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert!(users.iter().all(|c| c.client_type == 0));
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_clients_by_idle() {
+        let list = "clid=1 cid=1 client_database_id=1 client_nickname=a client_type=0 client_idle_time=500 client_created=1 client_lastconnected=1|clid=2 cid=1 client_database_id=2 client_nickname=b client_type=0 client_idle_time=120000 client_created=1 client_lastconnected=1|clid=3 cid=1 client_database_id=3 client_nickname=c client_type=0 client_idle_time=3000 client_created=1 client_lastconnected=1\n\rerror id=0 msg=ok\n\r";
+        let (mut client, handle) = mock_client(&[list, list]);
+        assert_eq!(
+            vec![
+                (client_id(2), Duration::from_secs(120)),
+                (client_id(3), Duration::from_secs(3)),
+                (client_id(1), Duration::from_millis(500))
+            ],
+            client.clients_by_idle().unwrap()
+        );
+        assert_eq!(
+            Some((client_id(2), Duration::from_secs(120))),
+            client.most_idle_client().unwrap()
+        );
+        drop(client);
+        assert_eq!(
+            vec!["clientlist -times", "clientlist -times", "quit"],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_server_host_info() {
+        let (mut client, handle) = mock_client(&[
+            "virtualserver_unique_identifier=abc= virtualserver_name=My\\sServer virtualserver_welcomemessage virtualserver_platform=Linux virtualserver_version=3.13.7\\s[Build:\\s1655727713] virtualserver_maxclients=32 virtualserver_clientsonline=3 virtualserver_queryclientsonline=1 virtualserver_uptime=90061 virtualserver_id=1 virtualserver_port=9987\n\rerror id=0 msg=ok\n\r",
+            "instance_uptime=3600 host_timestamp_utc=1577836800 virtualservers_running_total=1 virtualservers_total_maxclients=32 virtualservers_total_clients_online=3 virtualservers_total_channels_online=5 connection_ping=0\n\rerror id=0 msg=ok\n\r",
+        ]);
+        let server = client.server_info().unwrap();
+        assert_eq!("My Server", server.virtualserver_name);
+        assert_eq!("3.13.7 [Build: 1655727713]", server.virtualserver_version);
+        assert_eq!(9987, server.virtualserver_port);
+        assert_eq!(90061, server.virtualserver_uptime);
+        assert_eq!(Duration::from_secs(60 * 60 * 25 + 60 + 1), server.uptime());
+        let host = client.host_info().unwrap();
+        assert_eq!(Duration::from_secs(3600), host.uptime());
+        assert_eq!(5, host.virtualservers_total_channels_online);
+        drop(client);
+        assert_eq!(
+            vec!["serverinfo", "hostinfo", "quit"],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_raw_command_builder() {
+        let (mut client, handle) = mock_client(&[]);
+        client
+            .poke_client(client_id(3), "hello there | you")
+            .unwrap();
+        client
+            .raw_command(
+                raw::RawCommand::new("clientmove")
+                    .arg("clid", 3)
+                    .arg("cid", 5)
+                    .arg_escaped("cpw", "p w"),
+            )
+            .unwrap();
+        drop(client);
+        assert_eq!(
+            vec![
+                "clientpoke clid=3 msg=hello\\sthere\\s\\p\\syou",
+                "clientmove clid=3 cid=5 cpw=p\\sw",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_client_uid_from_clid() {
+        let (mut client, handle) = mock_client(&[
+            "cid=1 client_idle_time=20 client_unique_identifier=abc\\/d= client_nickname=foo\\sbar\n\rerror id=0 msg=ok\n\r",
+            // escaped backslash, must be unescaped only once
+            "cid=1 client_unique_identifier=e\\\\sg=\n\rerror id=0 msg=ok\n\r",
+            OK,
+            "cid=1 client_unique_identifier=abc\\/d=\n\rerror id=0 msg=ok\n\r",
+        ]);
+        assert_eq!("abc/d=", client.client_uid_from_clid(client_id(3)).unwrap());
+        assert_eq!("e\\sg=", client.client_uid_from_clid(client_id(4)).unwrap());
+        // cached
+        assert_eq!("abc/d=", client.client_uid_from_clid(client_id(3)).unwrap());
+        assert_eq!("e\\sg=", client.client_uid_from_clid(client_id(4)).unwrap());
+        // invalidated by server selection
+        client.select_server_by_id(2).unwrap();
+        assert_eq!("abc/d=", client.client_uid_from_clid(client_id(3)).unwrap());
+        drop(client);
+        assert_eq!(
+            vec![
+                "clientinfo clid=3",
+                "clientinfo clid=4",
+                "use sid=2",
+                "clientinfo clid=3",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_client_uid_cache_limit() {
+        let responses: Vec<String> = (0..=UID_CACHE_SIZE)
+            .map(|n| format!("client_unique_identifier=uid{}\n\rerror id=0 msg=ok\n\r", n))
+            .collect();
+        let responses: Vec<&str> = responses.iter().map(String::as_str).collect();
+        let (mut client, handle) = mock_client(&responses);
+        for n in 0..UID_CACHE_SIZE {
+            assert_eq!(
+                format!("uid{}", n),
+                client.client_uid_from_clid(client_id(n as u16)).unwrap()
+            );
+        }
+        // evicts the least recently used entry 0
+        client.client_uid_from_clid(client_id(1)).unwrap();
+        assert_eq!(
+            format!("uid{}", UID_CACHE_SIZE),
+            client
+                .client_uid_from_clid(client_id(UID_CACHE_SIZE as u16))
+                .unwrap()
+        );
+        assert_eq!(UID_CACHE_SIZE, client.uids.len());
+        assert!(client.uids.iter().all(|(clid, _)| *clid != client_id(0)));
+        drop(client);
+        assert_eq!(UID_CACHE_SIZE + 2, handle.join().unwrap().len());
+    }
+
+    #[test]
+    fn test_notify_interleaved() {
+        let (mut client, handle) = mock_client(&[
+            "clid=1 cid=2 client_database_id=3 client_nickname=foo client_type=0\n\rnotifyclientmoved ctid=4 reasonid=0 clid=5\n\rclid=6 cid=2 client_database_id=7 client_nickname=bar client_type=1\n\rerror id=0 msg=ok\n\r",
+            "notifychanneldeleted cid=9\n\rnotifyfoo a=b\n\rerror id=0 msg=ok\n\r",
+        ]);
+        let clients = client.online_clients().unwrap();
+        assert_eq!(2, clients.len());
+        assert_eq!("bar", clients[1].client_nickname);
+        client.raw_command_expect_empty("whoami").unwrap();
+        assert_eq!(
+            Some(event::Event::ClientMoved {
+                clid: client_id(5),
+                ctid: channel_id(4),
+                reasonid: ReasonId::None,
+                invoker: None,
+            }),
+            client.poll_event().unwrap()
+        );
+        assert_eq!(
+            Some(event::Event::ChannelDeleted {
+                cid: channel_id(9),
+                invoker: None
+            }),
+            client.poll_event().unwrap()
+        );
+        assert!(client.poll_event().is_err());
+        assert_eq!(None, client.poll_event().unwrap());
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_server_group_auto_perm() {
+        let (mut client, handle) = mock_client(&[]);
+        client
+            .server_group_auto_add_perm(AutoGroupType::ServerGuest, 12, 75, false, true)
+            .unwrap();
+        client
+            .server_group_auto_del_perm(AutoGroupType::ServerAdmin, 12)
+            .unwrap();
+        drop(client);
+        assert_eq!(
+            vec![
+                "servergroupautoaddperm sgtype=15 permid=12 permvalue=75 permnegated=0 permskip=1",
+                "servergroupautodelperm sgtype=45 permid=12",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_create_query_login() {
+        let (mut client, handle) = mock_client(&[
+            "client_login_name=my\\sbot client_login_password=+Ab3\\/xY\n\rerror id=0 msg=ok\n\r",
+            "client_login_password=secret\n\rerror id=0 msg=ok\n\r",
+            "error id=2561 msg=duplicate\\sentry\n\r",
+        ]);
+        assert_eq!(
+            ("my bot".to_string(), "+Ab3/xY".to_string()),
+            client.create_query_login("my bot").unwrap()
+        );
+        assert_eq!(
+            ("bot".to_string(), "secret".to_string()),
+            client.create_query_login("bot").unwrap()
+        );
+        let err = client.create_query_login("bot").unwrap_err();
+        assert!(err.error_response().unwrap().is_duplicate_entry());
+        drop(client);
+        assert_eq!(
+            vec![
+                "clientsetserverquerylogin client_login_name=my\\sbot",
+                "clientsetserverquerylogin client_login_name=bot",
+                "clientsetserverquerylogin client_login_name=bot",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_query_logins() {
+        let (mut client, handle) = mock_client(&[
+            "cldbid=1 sid=0 client_login_name=serveradmin|cldbid=5 sid=1 client_login_name=my\\sbot\n\rerror id=0 msg=ok\n\r",
+            "error id=1281 msg=database\\sempty\\sresult\\sset\n\r",
+            "cldbid=6 sid=1 client_login_name=bot client_login_password=abc\\/d\n\rerror id=0 msg=ok\n\r",
+            OK,
+        ]);
+        assert_eq!(
+            vec![
+                QueryLogin {
+                    cldbid: db_id(1),
+                    client_login_name: "serveradmin".to_string()
+                },
+                QueryLogin {
+                    cldbid: db_id(5),
+                    client_login_name: "my bot".to_string()
+                }
+            ],
+            client.query_login_list(None, 0, 50).unwrap()
+        );
+        assert!(client
+            .query_login_list(Some("my bot%"), 50, 50)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            ("bot".to_string(), "abc/d".to_string()),
+            client.query_login_add("bot", Some(db_id(6))).unwrap()
+        );
+        client.query_login_del(db_id(6)).unwrap();
+        drop(client);
+        assert_eq!(
+            vec![
+                "queryloginlist start=0 duration=50",
+                "queryloginlist pattern=my\\sbot% start=50 duration=50",
+                "queryloginadd client_login_name=bot cldbid=6",
+                "querylogindel cldbid=6",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_server_group_copy() {
+        let (mut client, handle) = mock_client(&[
+            "sgid=13\n\rerror id=0 msg=ok\n\r",
+            OK,
+            "error id=1282 msg=database\\sduplicate\\sentry\n\r",
+        ]);
+        assert_eq!(
+            group_id(13),
+            client
+                .clone_server_group(group_id(6), "Admin copy")
+                .unwrap()
+        );
+        assert_eq!(
+            None,
+            client
+                .server_group_copy(group_id(6), group_id(13), "", 1)
+                .unwrap()
+        );
+        assert!(client
+            .clone_server_group(group_id(6), "Admin copy")
+            .is_err());
+        drop(client);
+        assert_eq!(
+            vec![
+                "servergroupcopy ssgid=6 tsgid=0 name=Admin\\scopy type=1",
+                "servergroupcopy ssgid=6 tsgid=13 name= type=1",
+                "servergroupcopy ssgid=6 tsgid=0 name=Admin\\scopy type=1",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_join_channel() {
+        let (mut client, handle) = mock_client(&[
+            "virtualserver_status=online virtualserver_id=1 client_id=7 client_channel_id=1\n\rerror id=0 msg=ok\n\r",
+            OK,
+            "error id=770 msg=already\\smember\\sof\\schannel\n\r",
+            "error id=781 msg=invalid\\spassword\n\r",
+        ]);
+        client.join_channel(channel_id(4), None).unwrap();
+        client.join_channel(channel_id(4), None).unwrap();
+        assert!(client
+            .join_channel(channel_id(5), Some("pass word"))
+            .is_err());
+        drop(client);
+        assert_eq!(
+            vec![
+                "whoami",
+                "clientmove clid=7 cid=4",
+                "clientmove clid=7 cid=4",
+                "clientmove clid=7 cid=5 cpw=pass\\sword",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_total_byte_limit() {
+        let line = "a".repeat(1000);
+        let response = format!("{}\n\r{}\n\r{}\n\rerror id=0 msg=ok\n\r", line, line, line);
+        let (mut client, handle) = mock_client(&[&response]);
+        client.limit_total_bytes(2500);
+        let err = client.raw_command("clientlist").unwrap_err();
+        match err {
+            Ts3Error::ResponseLimit { kind, response, .. } => {
+                assert_eq!(LimitKind::TotalBytes, kind);
+                assert_eq!(vec![line.clone(), line], response)
+            }
+            e => panic!("unexpected error {:?}", e),
+        }
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_limit_kind() {
+        let response = "a\n\rb\n\rc\n\rerror id=0 msg=ok\n\r";
+        let long = format!("{}\n\rerror id=0 msg=ok\n\r", "a".repeat(200));
+        let (mut client, handle) = mock_client(&[response, &long]);
+        client.limit_lines(2);
+        let err = client.raw_command("clientlist").unwrap_err();
+        match err {
+            Ts3Error::ResponseLimit { kind, response, .. } => {
+                assert_eq!(LimitKind::Lines, kind);
+                assert_eq!(vec!["a", "b"], response);
+            }
+            e => panic!("unexpected error {:?}", e),
+        }
+        // remaining lines of the previous response are read first
+        client.limit_lines(LIMIT_READ_LINES);
+        client.raw_command("whoami").unwrap();
+        client.limit_line_bytes(100);
+        let err = client.raw_command("clientlist").unwrap_err();
+        assert!(matches!(
+            err,
+            Ts3Error::ResponseLimit {
+                kind: LimitKind::LineBytes,
+                ..
+            }
+        ));
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    thread_local! {
+        /// Sleeps of the mock clock, see [`mock_sleep`]
+        static SLEPT: std::cell::RefCell<Vec<Duration>> = const { std::cell::RefCell::new(Vec::new()) };
+    }
+
+    /// Mock clock for anti flood, records the requested sleep instead of sleeping
+    fn mock_sleep(duration: Duration) {
+        SLEPT.with(|s| s.borrow_mut().push(duration));
+    }
+
+    #[test]
+    fn test_anti_flood_interval() {
+        let (mut client, handle) = mock_client(&[]);
+        client.tx.sleep = mock_sleep;
+        client.raw_command("version").unwrap();
+        client.anti_flood(Some(Duration::from_secs(2)));
+        for _ in 0..3 {
+            client.raw_command("version").unwrap();
+        }
+        let slept = SLEPT.with(|s| s.take());
+        // first command after enabling is spaced from the previous one, too
+        assert_eq!(3, slept.len());
+        for sleep in slept {
+            assert!(sleep > Duration::from_millis(1900), "{:?}", sleep);
+            assert!(sleep <= Duration::from_secs(2));
+        }
+        client.anti_flood(None);
+        client.raw_command("version").unwrap();
+        assert!(SLEPT.with(|s| s.take()).is_empty());
+        drop(client);
+        assert_eq!(6, handle.join().unwrap().len());
+    }
+
+    #[test]
+    fn test_anti_flood_retry() {
+        let flood = "error id=524 msg=client\\sis\\sflooding\n\r";
+        let (mut client, handle) = mock_client(&[flood, flood, flood, flood, OK]);
+        client.tx.sleep = mock_sleep;
+        // no retry without anti flood
+        let err = client.raw_command("clientlist").unwrap_err();
+        assert!(err.error_response().unwrap().is_flooding());
+        client.anti_flood(Some(Duration::from_millis(10)));
+        // retried once
+        let err = client.raw_command("clientlist").unwrap_err();
+        assert!(err.error_response().unwrap().is_flooding());
+        // successful retry
+        client.raw_command("clientmove clid=1 cid=2").unwrap();
+        assert!(SLEPT.with(|s| s.take()).contains(&FLOOD_RETRY_WAIT));
+        drop(client);
+        assert_eq!(
+            vec![
+                "clientlist",
+                "clientlist",
+                "clientlist",
+                "clientmove clid=1 cid=2",
+                "clientmove clid=1 cid=2",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_send_message_chunked() {
+        // 'ä' is 2 bytes, chunk boundaries would split it
+        let msg = "aä ".repeat(10);
+        let chunks = QueryClient::chunk_message(&msg, 5);
+        assert_eq!(10, chunks.len());
+        assert_eq!(vec!["aä a", "ä a"], chunks[..2].to_vec());
+        assert!(chunks.iter().all(|c| c.len() <= 5));
+        assert_eq!(msg, chunks.concat());
+        assert_eq!(vec!["ä", "ä"], QueryClient::chunk_message("ää", 1));
+        assert!(QueryClient::chunk_message("", 5).is_empty());
+
+        let (mut client, handle) = mock_client(&[]);
+        client
+            .send_message_chunked(MessageTarget::Client(client_id(3)), "hello world|", 6)
+            .unwrap();
+        drop(client);
+        assert_eq!(
+            vec![
+                "sendtextmessage targetmode=1 target=3 msg=hello\\s",
+                "sendtextmessage targetmode=1 target=3 msg=world\\p",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_is_connected() {
+        let (mut client, handle) = mock_client(&[]);
+        assert!(client.is_connected());
+        client.ping().unwrap();
+        assert!(client.is_connected());
+        client.close().unwrap();
+        assert!(!client.is_connected());
+        handle.join().unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(b"TS3\n\rWelcome\n\r").unwrap();
+            // closes the connection
+        });
+        let client = QueryClient::new(addr).unwrap();
+        server.join().unwrap();
+        let start = Instant::now();
+        while client.is_connected() {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_lossy_utf8() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().unwrap();
+                stream
+                    .write_all(b"TS3\n\rWelcome\n\rclid=1 cid=2 client_database_id=3 client_nickname=b\xe4d client_type=0\n\rerror id=0 msg=ok\n\r")
+                    .unwrap();
+                // acknowledge the remaining commands, like quit
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap_or(0) > 0 {
+                    if stream.write_all(b"error id=0 msg=ok\n\r").is_err() {
+                        break;
+                    }
+                    line.clear();
+                }
+            }
+        });
+        let mut client = QueryClient::new(addr).unwrap();
+        let err = client.online_clients().unwrap_err();
+        assert!(matches!(err, Ts3Error::Utf8Error { .. }));
+        drop(client);
+
+        let mut client = QueryClient::new(addr).unwrap();
+        client.lossy_utf8(true);
+        let clients = client.online_clients().unwrap();
+        assert_eq!("b\u{fffd}d", clients[0].client_nickname);
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_server_group_perm_named() {
+        let (mut client, handle) = mock_client(&[]);
+        client
+            .server_group_add_perm_named(
+                group_id(7),
+                "i_channel_needed_join_power",
+                75,
+                false,
+                true,
+            )
+            .unwrap();
+        client
+            .server_group_del_perm_named(group_id(7), "b a|d")
+            .unwrap();
+
+        drop(client);
+        let commands = handle.join().unwrap();
+        assert_eq!(
+            "servergroupaddperm sgid=7 permsid=i_channel_needed_join_power permvalue=75 permnegated=0 permskip=1",
+            commands[0]
+        );
+        assert_eq!("servergroupdelperm sgid=7 permsid=b\\sa\\pd", commands[1]);
+    }
+
+    #[test]
+    fn test_bulk_move_kick() {
+        let (mut client, handle) =
+            mock_client(&[OK, "error id=512 msg=invalid\\sclientID\n\r", OK]);
+        let res = client
+            .move_clients(&[client_id(3), client_id(4)], channel_id(7), None)
+            .unwrap();
+        assert_eq!(client_id(3), res[0].0);
+        assert!(res[0].1.is_ok());
+        assert_eq!(client_id(4), res[1].0);
+        assert!(res[1].1.is_ok());
+        let res = client
+            .kick_clients(&[client_id(4), client_id(5)], true, Some("bye"))
+            .unwrap();
+        assert_eq!(2, res.len());
+        for (clid, (res_clid, res)) in [client_id(4), client_id(5)].iter().zip(&res) {
+            assert_eq!(clid, res_clid);
+            assert_eq!(
+                Some(512),
+                res.as_ref().unwrap_err().error_response().map(|r| r.id)
+            );
+        }
+        let res = client.kick_clients(&[client_id(6)], false, None).unwrap();
+        assert!(res[0].1.is_ok());
+        assert!(client
+            .move_clients(&[], channel_id(7), None)
+            .unwrap()
+            .is_empty());
+        drop(client);
+        assert_eq!(
+            vec![
+                "clientmove clid=3|clid=4 cid=7",
+                "clientkick clid=4|clid=5 reasonid=5 reasonmsg=bye",
+                "clientkick clid=6 reasonid=4",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_edit_no_modifications() {
+        let no_mods = "error id=1283 msg=database\\sno\\smodifications\n\r";
+        let (mut client, handle) = mock_client(&[
+            OK,
+            no_mods,
+            OK,
+            no_mods,
+            "error id=2568 msg=insufficient\\sclient\\spermissions\n\r",
+        ]);
+        let edit = ChannelEdit {
+            channel_topic: Some("new topic".to_string()),
+            ..Default::default()
+        };
+        client.edit_channel(channel_id(3), &edit).unwrap();
+        client.edit_channel(channel_id(3), &edit).unwrap();
+        let props = [("virtualserver_name", "My Server".to_string())];
+        client.server_edit(&props).unwrap();
+        client.server_edit(&props).unwrap();
+        let err = client.server_edit(&props).unwrap_err();
+        assert!(!err.is_no_modifications());
+        assert!(err.error_response().unwrap().is_permission_denied());
+        drop(client);
+        assert_eq!(
+            vec![
+                "channeledit cid=3 channel_topic=new\\stopic",
+                "channeledit cid=3 channel_topic=new\\stopic",
+                "serveredit virtualserver_name=My\\sServer",
+                "serveredit virtualserver_name=My\\sServer",
+                "serveredit virtualserver_name=My\\sServer",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_debug() {
+        let (mut client, handle) = mock_client(&[]);
+        client.login("serveradmin", "secretpass").unwrap();
+        client.select_server_by_port(9987).unwrap();
+        let debug = format!("{:?}", client);
+        assert!(debug.starts_with("QueryClient {"));
+        assert!(debug.contains("127.0.0.1"));
+        assert!(debug.contains("Port(9987)"));
+        assert!(!debug.contains("secretpass"));
+        drop(client);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_server_group_single_client() {
+        let (mut client, handle) = mock_client(&[]);
+        client
+            .server_group_add_client(group_id(6), db_id(42))
+            .unwrap();
+        client.server_group_add_clients(group_id(6), &[42]).unwrap();
+        client
+            .server_group_del_client(group_id(6), db_id(42))
+            .unwrap();
+        client.server_group_del_clients(group_id(6), &[42]).unwrap();
+        drop(client);
+        let commands = handle.join().unwrap();
+        assert_eq!("servergroupaddclient sgid=6 cldbid=42", commands[0]);
+        assert_eq!(commands[0], commands[1]);
+        assert_eq!("servergroupdelclient sgid=6 cldbid=42", commands[2]);
+        assert_eq!(commands[2], commands[3]);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_redact_command() {
+        assert_eq!(
+            "login serveradmin <redacted>",
+            redact_command("login serveradmin pass\n")
+        );
+        assert_eq!(
+            "login client_login_name=serveradmin client_login_password=<redacted>",
+            redact_command("login client_login_name=serveradmin client_login_password=pass")
+        );
+        assert_eq!(
+            "clientmove clid=5 cid=3 cpw=<redacted>",
+            redact_command("clientmove clid=5 cid=3 cpw=secret")
+        );
+        assert_eq!(
+            "privilegekeyuse token=<redacted>",
+            redact_command("privilegekeyuse token=abc")
+        );
+        assert_eq!("clientlist -uid", redact_command("clientlist -uid\n"));
+        let long = redact_command(&format!("serversnapshotdeploy {}", "ä".repeat(1000)));
+        assert!(long.starts_with("serversnapshotdeploy ää"));
+        assert!(long.ends_with("..."));
+        assert!(long.len() <= LIMIT_TRACE_COMMAND + 3);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_tracing() {
+        let (mut client, handle) = mock_client(&[
+            OK,
+            OK,
+            "error id=1281 msg=database\\sempty\\sresult\\sset\n\r",
+            OK,
+        ]);
+        client.login("serveradmin", "secretpass").unwrap();
+        client.raw_command("version").unwrap();
+        assert!(client.raw_command("clientdblist").is_err());
+        client.snapshot_deploy(&"a".repeat(70_000)).unwrap();
+        drop(client);
+        handle.join().unwrap();
+        assert!(logs_contain("command=login serveradmin <redacted>"));
+        assert!(logs_contain("command=version"));
+        assert!(logs_contain("lines=0"));
+        assert!(logs_contain("command failed"));
+        assert!(!logs_contain("secretpass"));
+        assert!(logs_contain("command=serversnapshotdeploy aaa"));
+        assert!(!logs_contain(&"a".repeat(LIMIT_TRACE_COMMAND)));
+    }
+
+    #[test]
+    fn test_on_line() {
+        let (mut client, handle) = mock_client(&[
+            "version=3.13.7 build=1655727713 platform=Linux\n\rerror id=0 msg=ok\n\r",
+            OK,
+        ]);
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let observed = lines.clone();
+        client.on_line(move |line| observed.lock().unwrap().push(line.to_owned()));
+        client.raw_command("version").unwrap();
+        client.clear_on_line();
+        client.raw_command("version").unwrap();
+        drop(client);
+        handle.join().unwrap();
+        assert_eq!(
+            vec![
+                "version=3.13.7 build=1655727713 platform=Linux",
+                "error id=0 msg=ok"
+            ],
+            *lines.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_move_already_member() {
+        let already_member = "error id=770 msg=already\\smember\\sof\\schannel\n\r";
+        let (mut client, handle) = mock_client(&[already_member, already_member]);
+        let err = client
+            .move_client(client_id(5), channel_id(3), None)
+            .unwrap_err();
+        assert!(err.is_already_member());
+        assert!(!err.is_no_modifications());
+        let res = client
+            .move_client(client_id(5), channel_id(3), None)
+            .or_else(|e| {
+                if e.is_already_member() {
+                    Ok(())
+                } else {
+                    Err(e)
+                }
+            });
+        assert!(res.is_ok());
+        drop(client);
+        handle.join().unwrap();
     }
 }
@@ -11,6 +11,8 @@
 //! // get inner connection with check for being alive
 //! // then perform a command on it
 //! let _ = conn.get()?.whoami(false)?;
+//! // common commands perform the check themselves
+//! conn.send_message(MessageTarget::Server, "hello")?;
 //! # Ok(())
 //! # }
 //! ```
@@ -20,7 +22,6 @@ use ::std::net::{SocketAddr, ToSocketAddrs};
 use ::std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use snafu::{OptionExt, ResultExt};
 
-const ERR_NAME_TAKEN: usize = 513;
 /// Max name length to allow unique names
 pub const MAX_LEN_NAME: usize = 20;
 
@@ -32,6 +33,8 @@ pub const DEFAULT_TIMEOUT_CMD: Duration = Duration::from_millis(1500);
 const KEY_CLIENT_ID_SELF: &str = "client_id";
 
 /// Config for creating a managed connection
+///
+/// The `Debug` output redacts the password and privilege key, configs can be logged safely.
 /// ```rust
 /// # use ts3_query::managed::ManagedConfig;
 /// # use ts3_query::*;
@@ -52,6 +55,28 @@ pub struct ManagedConfig {
     conn_timeout: Duration,
     cmd_timeout: Duration,
     name: Option<String>,
+    privilege_key: Option<String>,
+    legacy_tokens: bool,
+}
+
+impl std::fmt::Debug for ManagedConfig {
+    /// Redacts password and privilege key
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ManagedConfig")
+            .field("addr", &self.addr)
+            .field("user", &self.user)
+            .field("password", &"<redacted>")
+            .field("server_port", &self.server_port)
+            .field("conn_timeout", &self.conn_timeout)
+            .field("cmd_timeout", &self.cmd_timeout)
+            .field("name", &self.name)
+            .field(
+                "privilege_key",
+                &self.privilege_key.as_ref().map(|_| "<redacted>"),
+            )
+            .field("legacy_tokens", &self.legacy_tokens)
+            .finish()
+    }
 }
 
 impl ManagedConfig {
@@ -74,6 +99,8 @@ impl ManagedConfig {
             password,
             server_port,
             name: Default::default(),
+            privilege_key: None,
+            legacy_tokens: false,
             conn_timeout: DEFAULT_TIMEOUT_CONN,
             cmd_timeout: DEFAULT_TIMEOUT_CMD,
         })
@@ -90,6 +117,19 @@ impl ManagedConfig {
         self
     }
 
+    /// Set privilege key to use after selecting the server.
+    /// Allows a new query account to claim its group on connect.
+    pub fn privilege_key(mut self, key: String) -> Self {
+        self.privilege_key = Some(key);
+        self
+    }
+
+    /// Use legacy `token*` commands for privilege keys, see [`QueryClient::legacy_tokens`]
+    pub fn legacy_tokens(mut self, legacy: bool) -> Self {
+        self.legacy_tokens = legacy;
+        self
+    }
+
     /// Set connection timeout
     pub fn connection_timeout(mut self, timeout: Duration) -> Self {
         self.conn_timeout = timeout;
@@ -109,15 +149,34 @@ pub struct ManagedConnection {
     conn: QueryClient,
     last_ping: Instant,
     conn_id: Option<ClientId>,
+    events: Vec<NotifyEvent>,
+}
+
+// ManagedConnection has to stay Send + Sync, checked at compile time
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<ManagedConnection>();
+};
+
+impl std::fmt::Debug for ManagedConnection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ManagedConnection")
+            .field("addr", &self.cfg.addr)
+            .field("server_port", &self.cfg.server_port)
+            .field("name", &self.cfg.name)
+            .field("last_ping", &self.last_ping)
+            .field("conn_id", &self.conn_id)
+            .finish_non_exhaustive()
+    }
 }
 
 impl ManagedConnection {
-    fn connect(cfg: &ManagedConfig) -> Result<QueryClient> {
+    fn connect(cfg: &ManagedConfig, events: &[NotifyEvent]) -> Result<QueryClient> {
         // let mut conn = QueryClient::new((cfg.ip.as_ref(), cfg.port))?;
         let mut conn =
-            QueryClient::with_timeout(&cfg.addr, Some(cfg.conn_timeout), Some(cfg.cmd_timeout))?;
+            QueryClient::with_timeout(cfg.addr, Some(cfg.conn_timeout), Some(cfg.cmd_timeout))?;
+        conn.legacy_tokens(cfg.legacy_tokens);
         conn.login(&cfg.user, &cfg.password)?;
-        conn.select_server_by_port(cfg.server_port)?;
         if let Some(n) = cfg.name.as_ref() {
             // prevent underflow in name fallback
             if n.len() > MAX_LEN_NAME {
@@ -127,18 +186,41 @@ impl ManagedConnection {
                 }
                 .fail();
             }
-            Self::set_name_fallback(&mut conn, n)?;
+            Self::select_server_name_fallback(&mut conn, cfg.server_port, n)?;
+        } else {
+            conn.select_server_by_port(cfg.server_port)?;
+        }
+        Self::verify_server_port(&mut conn, cfg.server_port)?;
+        if let Some(key) = cfg.privilege_key.as_ref() {
+            conn.privilege_key_use(key)?;
+        }
+        for event in events {
+            conn.register_event(*event)?;
         }
         Ok(conn)
     }
 
-    /// Set name of client, fallback to name+last unix timestamp MS to make it unique
-    fn set_name_fallback(conn: &mut QueryClient, name: &str) -> Result<()> {
-        if let Err(e) = conn.rename(name) {
-            if e.error_response().map_or(true, |r| r.id != ERR_NAME_TAKEN) {
+    /// Verify via `whoami` that the server on `port` is selected
+    fn verify_server_port(conn: &mut QueryClient, port: u16) -> Result<()> {
+        let mut res = conn.whoami(false)?;
+        let selected: u16 = raw::int_val_parser(&mut res, "virtualserver_port")?;
+        if selected != port {
+            return InvalidResponse {
+                context: "selected server port differs from configured port, got: ",
+                data: selected.to_string(),
+            }
+            .fail();
+        }
+        Ok(())
+    }
+
+    /// Select server and set name of client, fallback to name+last unix timestamp MS to make it unique
+    fn select_server_name_fallback(conn: &mut QueryClient, port: u16, name: &str) -> Result<()> {
+        if let Err(e) = conn.select_server_by_port_with_name(port, name) {
+            if !e.error_response().is_some_and(|r| r.is_name_taken()) {
                 return Err(e);
             } else {
-                conn.rename(&Self::calc_name_retry(name))?;
+                conn.select_server_by_port_with_name(port, &Self::calc_name_retry(name))?;
             }
         }
         Ok(())
@@ -180,35 +262,98 @@ impl ManagedConnection {
         })
     }
 
-    /// Try creating a second connection, based on the configs of this one.
+    /// Try creating a second connection, based on the configs and event registrations of this one.
     /// `new_name` can specifiy a different connection client name.
     pub fn clone(&self, new_name: Option<String>) -> Result<Self> {
         let mut cfg = self.cfg.clone();
         if new_name.is_some() {
             cfg.name = new_name;
         }
-        Self::new(self.cfg.clone())
+        Self::with_events(cfg, self.events.clone())
     }
 
     /// Create new TS-Connection with an optional name
     pub fn new(config: ManagedConfig) -> Result<ManagedConnection> {
-        let conn = Self::connect(&config)?;
+        Self::with_events(config, Vec::new())
+    }
+
+    fn with_events(config: ManagedConfig, events: Vec<NotifyEvent>) -> Result<ManagedConnection> {
+        let conn = Self::connect(&config, &events)?;
         Ok(Self {
             conn,
             cfg: config,
             last_ping: Instant::now(),
             conn_id: None,
+            events,
         })
     }
 
+    /// Register for notifications of `event`, registrations are restored on reconnect.
+    pub fn register_event(&mut self, event: NotifyEvent) -> Result<()> {
+        self.get()?.register_event(event)?;
+        if !self.events.contains(&event) {
+            self.events.push(event);
+        }
+        Ok(())
+    }
+
+    /// Unregister from all notifications, registrations are no longer restored on reconnect.
+    pub fn unregister_all_events(&mut self) -> Result<()> {
+        self.get()?.unregister_all_events()?;
+        self.events.clear();
+        Ok(())
+    }
+
+    /// Returns the events registered via [`register_event`](ManagedConnection::register_event)
+    pub fn registered_events(&self) -> &[NotifyEvent] {
+        &self.events
+    }
+
     /// Force reconnect, may be called if server returns invalid data on call.
     /// Can happen if for example the firewall just drops packages for some time.
     pub fn force_reconnect(&mut self) -> Result<()> {
-        self.conn = Self::connect(&self.cfg)?;
+        self.conn = Self::connect(&self.cfg, &self.events)?;
         self.conn_id = None;
         Ok(())
     }
 
+    /// Run `f` on the active connection.
+    ///
+    /// Force-reconnects and retries once if `f` fails due to a [connection error](Ts3Error::is_connection_error),
+    /// for example when the connection died between the health check and the command.
+    pub fn execute<T, F>(&mut self, mut f: F) -> Result<T>
+    where
+        F: FnMut(&mut QueryClient) -> Result<T>,
+    {
+        match f(self.get()?) {
+            Err(e) if e.is_connection_error() => {
+                self.force_reconnect()?;
+                f(&mut self.conn)
+            }
+            res => res,
+        }
+    }
+
+    /// Returns online clients, see [`QueryClient::online_clients`]
+    pub fn online_clients(&mut self) -> Result<Vec<OnlineClient>> {
+        self.get()?.online_clients()
+    }
+
+    /// Send chat message, see [`QueryClient::send_message`]
+    pub fn send_message<T: AsRef<str>>(&mut self, target: MessageTarget, msg: T) -> Result<()> {
+        self.get()?.send_message(target, msg)
+    }
+
+    /// Returns whoami information, see [`QueryClient::whoami`]
+    pub fn whoami(&mut self, unescape: bool) -> Result<HashMap<String, Option<String>>> {
+        self.get()?.whoami(unescape)
+    }
+
+    /// Perform a raw command, see [`QueryClient::raw_command`]
+    pub fn raw_command<T: AsRef<str>>(&mut self, command: T) -> Result<Vec<String>> {
+        self.get()?.raw_command(command)
+    }
+
     /// Returns the active connection or fallbacks to reconnect
     /// Checks for connection health every 1 second between a get() call.
     pub fn get(&mut self) -> Result<&mut QueryClient> {
@@ -230,12 +375,13 @@ impl ManagedConnection {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test::channel_id;
 
     #[test]
     fn test_name_empty() {
         let name = ManagedConnection::calc_name_retry("");
         assert!(name.len() <= MAX_LEN_NAME);
-        assert!(name.len() > 0);
+        assert!(!name.is_empty());
         dbg!(name);
     }
 
@@ -273,7 +419,7 @@ mod tests {
         }
 
         // required for near-fit invariant
-        assert!(MAX_LEN_NAME > 3);
+        const _: () = assert!(MAX_LEN_NAME > 3);
         {
             // assert even for non-fit we have at least 2 random digits at the end
             let name_input = "123456789012345678";
@@ -294,4 +440,160 @@ mod tests {
         assert!(name.starts_with(&name_input[..MAX_LEN_NAME / 2]));
         assert!(name.len() <= MAX_LEN_NAME);
     }
+
+    #[test]
+    fn test_execute_retry() {
+        let (addr, handle) = crate::test::mock_server(&[], 2);
+        let cfg = ManagedConfig::new(addr, 9987, "serveradmin".into(), "pass".into()).unwrap();
+        let mut conn = ManagedConnection::new(cfg).unwrap();
+        let mut calls = 0;
+        let res = conn.execute(|c| {
+            calls += 1;
+            if calls == 1 {
+                return ConnectionClosed {}.fail();
+            }
+            c.whoami(false)
+        });
+        assert!(res.is_ok());
+        assert_eq!(2, calls);
+        drop(conn);
+        let commands = handle.join().unwrap();
+        assert_eq!(
+            2,
+            commands
+                .iter()
+                .filter(|c| *c == "login serveradmin pass")
+                .count()
+        );
+    }
+
+    #[test]
+    fn test_reconnect_events() {
+        let (addr, handle) = crate::test::mock_server(&[], 2);
+        let cfg = ManagedConfig::new(addr, 9987, "serveradmin".into(), "pass".into()).unwrap();
+        let mut conn = ManagedConnection::new(cfg).unwrap();
+        conn.register_event(NotifyEvent::Server).unwrap();
+        conn.register_event(NotifyEvent::Channel(channel_id(0)))
+            .unwrap();
+        conn.register_event(NotifyEvent::Server).unwrap();
+        assert_eq!(
+            &[NotifyEvent::Server, NotifyEvent::Channel(channel_id(0))],
+            conn.registered_events()
+        );
+        conn.force_reconnect().unwrap();
+        drop(conn);
+        let commands = handle.join().unwrap();
+        let count = |cmd: &str| commands.iter().filter(|c| *c == cmd).count();
+        assert_eq!(3, count("servernotifyregister event=server"));
+        assert_eq!(2, count("servernotifyregister event=channel id=0"));
+    }
+
+    #[test]
+    fn test_connect_privilege_key() {
+        let (addr, handle) = crate::test::mock_server(&[], 1);
+        let cfg = ManagedConfig::new(addr, 9987, "serveradmin".into(), "pass".into())
+            .unwrap()
+            .privilege_key("abc+def/ghi".into());
+        let conn = ManagedConnection::new(cfg).unwrap();
+        drop(conn);
+        assert_eq!(
+            vec![
+                "login serveradmin pass",
+                "use port=9987",
+                "whoami",
+                "privilegekeyuse token=abc+def\\/ghi",
+                "quit"
+            ],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_unregister_events() {
+        let (addr, handle) = crate::test::mock_server(&[], 2);
+        let cfg = ManagedConfig::new(addr, 9987, "serveradmin".into(), "pass".into()).unwrap();
+        let mut conn = ManagedConnection::new(cfg).unwrap();
+        conn.register_event(NotifyEvent::TextPrivate).unwrap();
+        conn.unregister_all_events().unwrap();
+        assert!(conn.registered_events().is_empty());
+        conn.force_reconnect().unwrap();
+        drop(conn);
+        let commands = handle.join().unwrap();
+        let count = |cmd: &str| commands.iter().filter(|c| *c == cmd).count();
+        assert_eq!(1, count("servernotifyregister event=textprivate"));
+        assert_eq!(1, count("servernotifyunregister"));
+    }
+
+    #[test]
+    fn test_connect_legacy_tokens() {
+        let (addr, handle) = crate::test::mock_server(&[], 1);
+        let cfg = ManagedConfig::new(addr, 9987, "serveradmin".into(), "pass".into())
+            .unwrap()
+            .privilege_key("abc".into())
+            .legacy_tokens(true);
+        let conn = ManagedConnection::new(cfg).unwrap();
+        drop(conn);
+        let commands = handle.join().unwrap();
+        assert_eq!("tokenuse token=abc", commands[3]);
+    }
+
+    #[test]
+    fn test_connect_port_mismatch() {
+        let whoami =
+            crate::test::WHOAMI.replace("virtualserver_port=9987", "virtualserver_port=9988");
+        let (addr, handle) =
+            crate::test::mock_server(&[crate::test::OK, crate::test::OK, &whoami], 1);
+        let cfg = ManagedConfig::new(addr, 9987, "serveradmin".into(), "pass".into()).unwrap();
+        match ManagedConnection::new(cfg) {
+            Err(Ts3Error::InvalidResponse { data, .. }) => assert_eq!("9988", data),
+            Err(e) => panic!("unexpected error {:?}", e),
+            Ok(_) => panic!("expected port mismatch"),
+        }
+        assert_eq!(
+            vec!["login serveradmin pass", "use port=9987", "whoami", "quit"],
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_pass_through_reconnect() {
+        let (addr, handle) = crate::test::mock_server(&[], 2);
+        let cfg = ManagedConfig::new(addr, 9987, "serveradmin".into(), "pass".into()).unwrap();
+        let mut conn = ManagedConnection::new(cfg).unwrap();
+        // kill the inner connection
+        conn.conn.shutdown().unwrap();
+        conn.send_message(MessageTarget::Server, "hi there")
+            .unwrap();
+        assert!(conn.online_clients().unwrap().is_empty());
+        conn.raw_command("version").unwrap();
+        drop(conn);
+        let commands = handle.join().unwrap();
+        let count = |cmd: &str| commands.iter().filter(|c| *c == cmd).count();
+        assert_eq!(2, count("login serveradmin pass"));
+        assert_eq!(1, count("sendtextmessage targetmode=3 msg=hi\\sthere"));
+        assert_eq!(1, count("clientlist"));
+        assert_eq!(1, count("version"));
+    }
+
+    #[test]
+    fn test_debug_redacted() {
+        let (addr, handle) = crate::test::mock_server(&[], 1);
+        let cfg = ManagedConfig::new(addr, 9987, "serveradmin".into(), "secretpass".into())
+            .unwrap()
+            .name("bot".into())
+            .privilege_key("secretkey".into());
+        for debug in &[format!("{:?}", cfg), format!("{:#?}", cfg)] {
+            assert!(debug.contains("serveradmin"));
+            assert!(debug.contains("password: \"<redacted>\""));
+            assert!(!debug.contains("secretpass"));
+            assert!(!debug.contains("secretkey"));
+        }
+        let conn = ManagedConnection::new(cfg).unwrap();
+        let debug = format!("{:?}", conn);
+        assert!(debug.contains("\"bot\""));
+        assert!(debug.contains("9987"));
+        assert!(!debug.contains("secretpass"));
+        drop(conn);
+        handle.join().unwrap();
+    }
 }
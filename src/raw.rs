@@ -1,5 +1,11 @@
 //! Module with helpers for raw-calls
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::{self, Write},
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use snafu::ResultExt;
 
@@ -35,8 +41,9 @@ fn parse_single_line_hashmap(
     map: &mut HashMap<String, Option<String>>,
     unescape: bool,
 ) {
-    line.split_whitespace().for_each(|e| {
-        let mut entries = e.split('=');
+    // only split on spaces, other whitespace like U+2000 is not escaped
+    line.split(' ').for_each(|e| {
+        let mut entries = e.splitn(2, '=');
         if let (Some(k), Some(v)) = (entries.next(), entries.next()) {
             let v = if unescape {
                 unescape_val(v)
@@ -64,7 +71,7 @@ pub fn parse_multi_hashmap(
 ) -> Vec<HashMap<String, Option<String>>> {
     let v: Vec<HashMap<String, Option<String>>> = input
         .into_iter()
-        .map(|l| {
+        .flat_map(|l| {
             l.split('|')
                 .map(|s| {
                     let mut map = HashMap::new();
@@ -73,15 +80,168 @@ pub fn parse_multi_hashmap(
                 })
                 .collect::<Vec<HashMap<String, Option<String>>>>()
         })
-        .flatten()
         .collect();
     v
 }
 
+/// Parse multi-hashmap response like [parse_multi_hashmap], but skipping empty entries.
+///
+/// Empty entries are caused by trailing or repeated `|`.
+/// ```rust
+/// use ts3_query::*;
+///
+/// let input: Vec<String> = vec!["clid=1|clid=2|".to_owned()];
+/// assert_eq!(3, raw::parse_multi_hashmap(input.clone(), false).len());
+/// assert_eq!(2, raw::parse_multi_hashmap_non_empty(input, false).len());
+/// ```
+pub fn parse_multi_hashmap_non_empty(
+    input: Vec<String>,
+    unescape: bool,
+) -> Vec<HashMap<String, Option<String>>> {
+    let mut v = parse_multi_hashmap(input, unescape);
+    v.retain(|m| !m.is_empty());
+    v
+}
+
+/// Parse multi-hashmap response like [parse_multi_hashmap], but borrowing from the input.
+///
+/// Keys are not copied and values are only allocated if unescaping changes them.
+///
+/// ```rust
+/// use ts3_query::*;
+///
+/// let input: Vec<String> = vec!["clid=1 client_nickname=foo\\sbar|clid=2 client_away_message".to_owned()];
+/// let res = raw::parse_multi_borrowed(&input, true);
+/// assert_eq!(Some("foo bar"), res[0]["client_nickname"].as_deref());
+/// assert_eq!(None, res[1]["client_away_message"]);
+/// ```
+pub fn parse_multi_borrowed(
+    input: &[String],
+    unescape: bool,
+) -> Vec<HashMap<&str, Option<Cow<'_, str>>>> {
+    input
+        .iter()
+        .flat_map(|l| l.split('|'))
+        .map(|s| {
+            let mut map = HashMap::new();
+            s.split(' ').for_each(|e| {
+                let mut entries = e.splitn(2, '=');
+                if let (Some(k), Some(v)) = (entries.next(), entries.next()) {
+                    let v = if unescape && v.contains('\\') {
+                        Cow::Owned(unescape_val(v))
+                    } else {
+                        Cow::Borrowed(v)
+                    };
+                    map.insert(k, Some(v));
+                } else if !e.is_empty() {
+                    map.insert(e, None);
+                }
+            });
+            map
+        })
+        .collect()
+}
+
+/// Type parseable from a single record of a response, see [parse_grouped]
+pub trait FromQueryString: Sized {
+    /// Parse from the key-value pairs of a record, values are not unescaped
+    fn from_query(data: HashMap<String, Option<String>>) -> crate::Result<Self>;
+}
+
+/// Parse list response, splitting it on `|` into records and parsing each as `T`.
+///
+/// Comma separated lists inside a record, like `client_servergroups`, can be parsed by [int_list_val_parser].
+pub fn parse_grouped<T: FromQueryString>(input: Vec<String>) -> crate::Result<Vec<T>> {
+    parse_multi_hashmap_non_empty(input, false)
+        .into_iter()
+        .map(T::from_query)
+        .collect()
+}
+
 /// Escape string for query commands send via raw function
 pub fn escape_arg<T: AsRef<str>>(input: T) -> String {
-    let res: Vec<u8> = Escape::new(input.as_ref().bytes()).collect();
-    String::from_utf8(res).unwrap()
+    let mut res = String::with_capacity(input.as_ref().len());
+    escape_arg_into(input.as_ref(), &mut res);
+    res
+}
+
+/// Escape string for query commands like [escape_arg], appending the result to `out`.
+///
+/// ```rust
+/// use ts3_query::*;
+///
+/// let mut cmd = String::from("clientupdate client_nickname=");
+/// raw::escape_arg_into("my bot", &mut cmd);
+/// assert_eq!("clientupdate client_nickname=my\\sbot", cmd);
+/// ```
+pub fn escape_arg_into(input: &str, out: &mut String) {
+    out.extend(Escape::new(input.chars()));
+}
+
+/// Builder for raw query commands, see [raw_command](crate::QueryClient::raw_command).
+///
+/// Values added via [arg_escaped](RawCommand::arg_escaped) are escaped,
+/// [arg](RawCommand::arg) is intended for known-safe values like numeric IDs and is not escaped.
+///
+/// ```rust
+/// use ts3_query::raw::RawCommand;
+///
+/// let cmd = RawCommand::new("clientedit")
+///     .arg("clid", 7)
+///     .arg_escaped("client_description", "a bot")
+///     .flag("-uid")
+///     .build();
+/// assert_eq!("clientedit clid=7 client_description=a\\sbot -uid", cmd);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawCommand {
+    cmd: String,
+}
+
+impl RawCommand {
+    /// Start a new command
+    pub fn new<T: AsRef<str>>(command: T) -> Self {
+        Self {
+            cmd: command.as_ref().to_owned(),
+        }
+    }
+
+    /// Add `key=value` without escaping the value
+    pub fn arg<T: fmt::Display>(mut self, key: &str, value: T) -> Self {
+        write!(self.cmd, " {}={}", key, value).unwrap();
+        self
+    }
+
+    /// Add `key=value`, escaping the value
+    pub fn arg_escaped<T: AsRef<str>>(mut self, key: &str, value: T) -> Self {
+        write!(self.cmd, " {}=", key).unwrap();
+        escape_arg_into(value.as_ref(), &mut self.cmd);
+        self
+    }
+
+    /// Add a flag like `-uid`
+    pub fn flag(mut self, flag: &str) -> Self {
+        self.cmd.push(' ');
+        self.cmd.push_str(flag);
+        self
+    }
+
+    /// Returns the final command
+    pub fn build(self) -> String {
+        self.cmd
+    }
+}
+
+impl AsRef<str> for RawCommand {
+    fn as_ref(&self) -> &str {
+        &self.cmd
+    }
+}
+
+impl fmt::Display for RawCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.cmd)
+    }
 }
 
 /// Unescape server response
@@ -122,72 +282,72 @@ const LONGEST_ESCAPE: usize = 2;
 ///
 /// Can be used like Escape::new(String)
 #[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
-struct Escape<I: Iterator<Item = u8>> {
+struct Escape<I: Iterator<Item = char>> {
     inner: I,
-    buffer: u8,
+    buffer: char,
 }
 
-impl<I: Iterator<Item = u8>> Escape<I> {
-    /// Create an iterator adaptor which will escape all the bytes of internal iterator.
+impl<I: Iterator<Item = char>> Escape<I> {
+    /// Create an iterator adaptor which will escape all the chars of internal iterator.
     pub fn new(i: I) -> Escape<I> {
         Escape {
             inner: i,
-            buffer: 0,
+            buffer: '\0',
         }
     }
 }
 
-impl<I: Iterator<Item = u8>> Iterator for Escape<I> {
-    type Item = u8;
+impl<I: Iterator<Item = char>> Iterator for Escape<I> {
+    type Item = char;
 
-    fn next(&mut self) -> Option<u8> {
-        if self.buffer != 0 {
-            let ret = Some(self.buffer as u8);
-            self.buffer = 0;
+    fn next(&mut self) -> Option<char> {
+        if self.buffer != '\0' {
+            let ret = Some(self.buffer);
+            self.buffer = '\0';
             ret
         } else if let Some(ch) = self.inner.next() {
             match ch {
                 // reverse hex representation
                 // as we take them in that order
-                b'\\' | b'/' => {
+                '\\' | '/' => {
                     self.buffer = ch;
-                    Some(b'\\')
+                    Some('\\')
                 }
-                b' ' => {
-                    self.buffer = b's';
-                    Some(b'\\')
+                ' ' => {
+                    self.buffer = 's';
+                    Some('\\')
                 }
-                b'|' => {
-                    self.buffer = b'p';
-                    Some(b'\\')
+                '|' => {
+                    self.buffer = 'p';
+                    Some('\\')
                 }
-                7 => {
-                    self.buffer = b'a';
-                    Some(b'\\')
+                '\u{7}' => {
+                    self.buffer = 'a';
+                    Some('\\')
                 }
-                8 => {
-                    self.buffer = b'b';
-                    Some(b'\\')
+                '\u{8}' => {
+                    self.buffer = 'b';
+                    Some('\\')
                 }
-                12 => {
-                    self.buffer = b'f';
-                    Some(b'\\')
+                '\u{c}' => {
+                    self.buffer = 'f';
+                    Some('\\')
                 }
-                b'\n' => {
-                    self.buffer = b'n';
-                    Some(b'\\')
+                '\n' => {
+                    self.buffer = 'n';
+                    Some('\\')
                 }
-                b'\r' => {
-                    self.buffer = b'r';
-                    Some(b'\\')
+                '\r' => {
+                    self.buffer = 'r';
+                    Some('\\')
                 }
-                b'\t' => {
-                    self.buffer = b't';
-                    Some(b'\\')
+                '\t' => {
+                    self.buffer = 't';
+                    Some('\\')
                 }
-                11 => {
-                    self.buffer = b'v';
-                    Some(b'\\')
+                '\u{b}' => {
+                    self.buffer = 'v';
+                    Some('\\')
                 }
                 _ => Some(ch),
             }
@@ -211,6 +371,8 @@ impl<I: Iterator<Item = u8>> Iterator for Escape<I> {
 
 /// Helper function to read int value list from line-hashmap, (re)moves value.
 ///
+/// Empty values and keys without value result in an empty list.
+///
 /// ```rust
 /// use ts3_query::*;
 /// use std::collections::HashMap;
@@ -219,8 +381,10 @@ impl<I: Iterator<Item = u8>> Iterator for Escape<I> {
 ///     vec![("abc".to_string(), Some("123,345,123".to_string())),
 ///     ("def".to_string(), None)]
 ///     .into_iter().collect();
-/// let v: Vec<i32> = raw::int_list_val_parser(&mut v, "abc").unwrap();
-/// assert_eq!(vec![123,345,123],v);
+/// let list: Vec<i32> = raw::int_list_val_parser(&mut v, "abc").unwrap();
+/// assert_eq!(vec![123,345,123],list);
+/// let list: Vec<i32> = raw::int_list_val_parser(&mut v, "def").unwrap();
+/// assert!(list.is_empty());
 /// ```
 pub fn int_list_val_parser<T>(
     data: &mut HashMap<String, Option<String>>,
@@ -229,7 +393,10 @@ pub fn int_list_val_parser<T>(
 where
     T: FromStr<Err = std::num::ParseIntError>,
 {
-    let v = string_val_parser(data, key)?;
+    let v = match string_val_parser_opt(data, key)? {
+        Some(v) if !v.is_empty() => v,
+        _ => return Ok(Vec::new()),
+    };
     let values: Vec<T> = v
         .split(",")
         .map(|v| {
@@ -243,24 +410,45 @@ where
 
 /// Helper function to retrieve bool value from line-hashmap, (re)moves value.
 ///
+/// Accepts integers (`>0` is true) and case-insensitive `true`/`false`.
+///
 /// ```rust
 /// use ts3_query::*;
 /// use std::collections::HashMap;
 ///
 /// let mut v: HashMap<String, Option<String>> =
 ///     vec![("abc".to_string(), Some("1".to_string())),
-///     ("def".to_string(), Some("0".to_string()))]
+///     ("def".to_string(), Some("0".to_string())),
+///     ("ghi".to_string(), Some("True".to_string())),
+///     ("jkl".to_string(), Some("yes".to_string()))]
 ///     .into_iter().collect();
 /// assert_eq!(true,raw::bool_val_parser(&mut v, "abc").unwrap());
 /// assert_eq!(false,raw::bool_val_parser(&mut v, "def").unwrap());
+/// assert_eq!(true,raw::bool_val_parser(&mut v, "ghi").unwrap());
+/// assert!(raw::bool_val_parser(&mut v, "jkl").is_err());
 /// assert!(raw::bool_val_parser(&mut v, "foobar").is_err());
 /// ```
 pub fn bool_val_parser(
     data: &mut HashMap<String, Option<String>>,
     key: &'static str,
 ) -> crate::Result<bool> {
-    let val: i32 = int_val_parser(data, key)?;
-    Ok(val > 0)
+    let v = data
+        .remove(key)
+        .ok_or_else(|| crate::NoEntryResponse { key }.build())?
+        .ok_or_else(|| crate::NoValueResponse { key }.build())?;
+    if let Ok(val) = v.parse::<i32>() {
+        Ok(val > 0)
+    } else if v.eq_ignore_ascii_case("true") {
+        Ok(true)
+    } else if v.eq_ignore_ascii_case("false") {
+        Ok(false)
+    } else {
+        crate::InvalidResponse {
+            context: "expected bool, got ",
+            data: v,
+        }
+        .fail()
+    }
 }
 
 /// Helper function to retrieve optional string value from line-hashmap, (re)moves value.
@@ -311,12 +499,12 @@ where
         .ok_or_else(|| crate::NoEntryResponse { key }.build())?;
 
     if let Some(v) = v {
-        return Ok(Some(
+        Ok(Some(
             v.parse()
                 .with_context(|| crate::InvalidIntResponse { data: v })?,
-        ));
+        ))
     } else {
-        return Ok(None);
+        Ok(None)
     }
 }
 
@@ -344,8 +532,59 @@ where
         .remove(key)
         .ok_or_else(|| crate::NoEntryResponse { key }.build())?
         .ok_or_else(|| crate::NoValueResponse { key }.build())?;
-    Ok(v.parse()
-        .with_context(|| crate::InvalidIntResponse { data: v })?)
+    v.parse()
+        .with_context(|| crate::InvalidIntResponse { data: v })
+}
+
+/// Helper function to retrieve and parse float value from line-hashmap, (re)moves value.
+///
+/// ```rust
+/// use ts3_query::*;
+/// use std::collections::HashMap;
+///
+/// let mut v: HashMap<String, Option<String>> =
+///     vec![("abc".to_string(), Some("0.125".to_string())),
+///     ("def".to_string(), None)]
+///     .into_iter().collect();
+/// assert_eq!(0.125,raw::float_val_parser::<f64>(&mut v, "abc").unwrap());
+/// assert!(raw::float_val_parser::<f64>(&mut v, "def").is_err());
+/// ```
+pub fn float_val_parser<T>(
+    data: &mut HashMap<String, Option<String>>,
+    key: &'static str,
+) -> crate::Result<T>
+where
+    T: FromStr<Err = std::num::ParseFloatError>,
+{
+    let v = data
+        .remove(key)
+        .ok_or_else(|| crate::NoEntryResponse { key }.build())?
+        .ok_or_else(|| crate::NoValueResponse { key }.build())?;
+    v.parse()
+        .with_context(|| crate::InvalidFloatResponse { data: v })
+}
+
+/// Helper function to retrieve unix timestamp (seconds) from line-hashmap, (re)moves value.
+///
+/// ```rust
+/// use ts3_query::*;
+/// use std::collections::HashMap;
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let mut v: HashMap<String, Option<String>> =
+///     vec![("client_created".to_string(), Some("1577836800".to_string())),
+///     ("def".to_string(), None)]
+///     .into_iter().collect();
+/// assert_eq!(UNIX_EPOCH + Duration::from_secs(1_577_836_800),
+///     raw::timestamp_val_parser(&mut v, "client_created").unwrap());
+/// assert!(raw::timestamp_val_parser(&mut v, "def").is_err());
+/// ```
+pub fn timestamp_val_parser(
+    data: &mut HashMap<String, Option<String>>,
+    key: &'static str,
+) -> crate::Result<SystemTime> {
+    let secs: u64 = int_val_parser(data, key)?;
+    Ok(UNIX_EPOCH + Duration::from_secs(secs))
 }
 
 /// Helper function to retrieve string value from line-hashmap, (re)moves value.
@@ -365,7 +604,7 @@ pub fn string_val_parser(
     data: &mut HashMap<String, Option<String>>,
     key: &'static str,
 ) -> crate::Result<String> {
-    Ok(string_val_parser_opt(data, key)?.ok_or_else(|| crate::NoValueResponse { key }.build())?)
+    string_val_parser_opt(data, key)?.ok_or_else(|| crate::NoValueResponse { key }.build())
 }
 
 #[cfg(test)]
@@ -379,7 +618,7 @@ mod test {
     pub fn test_escaped_input() {
         let v: Vec<u8> = vec![b'\\', b'/', 7, 8, 12, 11, b'\t', b'\r', b'\n'];
 
-        assert_eq!(true, String::from_utf8(v).is_ok());
+        assert!(String::from_utf8(v).is_ok());
     }
 
     #[test]
@@ -389,27 +628,22 @@ mod test {
         parse_single_line_hashmap(v, &mut map, false);
         assert_eq!(
             Some("1776"),
-            map.get("clid")
-                .map(|v| v.as_ref().map(|v| v.as_str()))
-                .flatten()
+            map.get("clid").and_then(|v| v.as_ref().map(|v| v.as_str()))
         );
         assert_eq!(
             Some("18106"),
             map.get("client_database_id")
-                .map(|v| v.as_ref().map(|v| v.as_str()))
-                .flatten()
+                .and_then(|v| v.as_ref().map(|v| v.as_str()))
         );
         assert_eq!(
             Some("FOOBAR\\s\\p\\sNora\\s\\p\\sLaptop"),
             map.get("client_nickname")
-                .map(|v| v.as_ref().map(|v| v.as_str()))
-                .flatten()
+                .and_then(|v| v.as_ref().map(|v| v.as_str()))
         );
         assert_eq!(
             Some("1"),
             map.get("client_type")
-                .map(|v| v.as_ref().map(|v| v.as_str()))
-                .flatten()
+                .and_then(|v| v.as_ref().map(|v| v.as_str()))
         );
         // verify public function does the same
         assert_eq!(map, parse_hashmap(vec![v.to_string()], false));
@@ -418,27 +652,22 @@ mod test {
         parse_single_line_hashmap(v, &mut map, true);
         assert_eq!(
             Some("1776"),
-            map.get("clid")
-                .map(|v| v.as_ref().map(|v| v.as_str()))
-                .flatten()
+            map.get("clid").and_then(|v| v.as_ref().map(|v| v.as_str()))
         );
         assert_eq!(
             Some("18106"),
             map.get("client_database_id")
-                .map(|v| v.as_ref().map(|v| v.as_str()))
-                .flatten()
+                .and_then(|v| v.as_ref().map(|v| v.as_str()))
         );
         assert_eq!(
             Some(r#"FOOBAR | Nora | Laptop"#),
             map.get("client_nickname")
-                .map(|v| v.as_ref().map(|v| v.as_str()))
-                .flatten()
+                .and_then(|v| v.as_ref().map(|v| v.as_str()))
         );
         assert_eq!(
             Some("1"),
             map.get("client_type")
-                .map(|v| v.as_ref().map(|v| v.as_str()))
-                .flatten()
+                .and_then(|v| v.as_ref().map(|v| v.as_str()))
         );
         // verify public function does the same
         assert_eq!(map, parse_hashmap(vec![v.to_string()], true));
@@ -468,30 +697,183 @@ mod test {
             Some("1776"),
             first
                 .get("clid")
-                .map(|v| v.as_ref().map(|v| v.as_str()))
-                .flatten()
+                .and_then(|v| v.as_ref().map(|v| v.as_str()))
         );
         assert_eq!(
             Some("18106"),
             first
                 .get("client_database_id")
-                .map(|v| v.as_ref().map(|v| v.as_str()))
-                .flatten()
+                .and_then(|v| v.as_ref().map(|v| v.as_str()))
         );
         let second = &result[1];
         assert_eq!(
             Some(r#"FOOBAR | Nora | Laptop"#),
             second
                 .get("client_nickname")
-                .map(|v| v.as_ref().map(|v| v.as_str()))
-                .flatten()
+                .and_then(|v| v.as_ref().map(|v| v.as_str()))
         );
         assert_eq!(
             Some("1"),
             second
                 .get("client_type")
-                .map(|v| v.as_ref().map(|v| v.as_str()))
-                .flatten()
+                .and_then(|v| v.as_ref().map(|v| v.as_str()))
+        );
+    }
+
+    #[test]
+    pub fn verify_multi_map_borrowed() {
+        let v = "clid=1776 client_database_id=18106|client_nickname=FOOBAR\\s\\p\\sNora\\s\\p\\sLaptop client_type=1";
+        let input = vec![v.to_string()];
+        let result = parse_multi_borrowed(&input, true);
+        let first = &result[0];
+        assert_eq!(Some("1776"), first.get("clid").and_then(|v| v.as_deref()));
+        assert!(matches!(first.get("clid"), Some(Some(Cow::Borrowed(_)))));
+        assert_eq!(
+            Some("18106"),
+            first.get("client_database_id").and_then(|v| v.as_deref())
+        );
+        let second = &result[1];
+        assert_eq!(
+            Some(r#"FOOBAR | Nora | Laptop"#),
+            second.get("client_nickname").and_then(|v| v.as_deref())
+        );
+        assert_eq!(
+            Some("1"),
+            second.get("client_type").and_then(|v| v.as_deref())
+        );
+
+        // same as owned version
+        let owned = parse_multi_hashmap(input.clone(), true);
+        for (b, o) in result.iter().zip(owned.iter()) {
+            assert_eq!(b.len(), o.len());
+            for (k, v) in b {
+                assert_eq!(o[*k].as_deref(), v.as_deref());
+            }
+        }
+    }
+
+    #[test]
+    fn verify_multi_map_non_empty() {
+        let input = vec!["clid=1 cid=2||clid=3 cid=4|".to_string(), "|".to_string()];
+        let res = parse_multi_hashmap_non_empty(input, false);
+        assert_eq!(2, res.len());
+        assert_eq!(Some(&Some("1".to_string())), res[0].get("clid"));
+        assert_eq!(Some(&Some("3".to_string())), res[1].get("clid"));
+    }
+
+    #[test]
+    fn verify_value_with_equals() {
+        let input = vec!["token=abc=def clid=1".to_string()];
+        let res = parse_hashmap(input.clone(), false);
+        assert_eq!(Some(&Some("abc=def".to_string())), res.get("token"));
+        let res = parse_multi_borrowed(&input, false);
+        assert_eq!(Some("abc=def"), res[0]["token"].as_deref());
+    }
+
+    #[test]
+    fn verify_escape_edge_cases() {
+        for v in &[
+            "\0",
+            "a\0b\0",
+            "\u{7f}\u{80}\u{ff}",
+            "\u{10FFFF}",
+            "\\s\\p",
+            "\\",
+            "/\\/",
+            "\u{7}\u{8}\u{b}\u{c}\t\r\n",
+        ] {
+            assert_eq!(*v, unescape_val(escape_arg(v)));
+        }
+        assert_eq!("a\0b", escape_arg("a\0b"));
+        // unicode whitespace is not escaped and must not split values
+        let map = parse_hashmap(vec![format!("msg={} a=1", escape_arg("x\u{2000}y"))], true);
+        assert_eq!(Some(&Some("x\u{2000}y".to_string())), map.get("msg"));
+        let input = vec![format!("msg={}", escape_arg("\u{a0}"))];
+        assert_eq!(
+            Some("\u{a0}"),
+            parse_multi_borrowed(&input, true)[0]["msg"].as_deref()
+        );
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_escape_roundtrip(v in proptest::prelude::any::<String>()) {
+            proptest::prop_assert_eq!(&v, &unescape_val(escape_arg(&v)));
+        }
+
+        #[test]
+        fn prop_escape_no_separators(v in proptest::prelude::any::<String>()) {
+            let escaped = escape_arg(&v);
+            proptest::prop_assert!(!escaped.contains([' ', '|', '\n', '\r']));
+        }
+
+        #[test]
+        fn prop_parse_roundtrip(v in proptest::prelude::any::<String>()) {
+            let map = parse_multi_hashmap(vec![format!("a=1 msg={}|a=2", escape_arg(&v))], true);
+            proptest::prop_assert_eq!(2, map.len());
+            proptest::prop_assert_eq!(Some(&Some(v)), map[0].get("msg"));
+        }
+    }
+
+    #[test]
+    fn verify_escape_into() {
+        let mut out = String::from("prefix ");
+        escape_arg_into("a b|c", &mut out);
+        escape_arg_into("ä/ö\n", &mut out);
+        assert_eq!(
+            format!("prefix {}{}", escape_arg("a b|c"), escape_arg("ä/ö\n")),
+            out
+        );
+    }
+
+    #[test]
+    fn verify_raw_command() {
+        let cmd = RawCommand::new("clientedit")
+            .arg("clid", 7)
+            .arg_escaped("client_description", "a b|c")
+            .flag("-uid")
+            .arg_escaped("client_nickname", "ä/ö")
+            .flag("-away");
+        assert_eq!(
+            "clientedit clid=7 client_description=a\\sb\\pc -uid client_nickname=ä\\/ö -away",
+            cmd.as_ref()
+        );
+        assert_eq!(cmd.to_string(), cmd.clone().build());
+        assert_eq!("whoami", RawCommand::new("whoami").build());
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Grouped {
+        id: u32,
+        groups: Vec<u32>,
+    }
+
+    impl FromQueryString for Grouped {
+        fn from_query(mut data: HashMap<String, Option<String>>) -> crate::Result<Self> {
+            Ok(Grouped {
+                id: int_val_parser(&mut data, "id")?,
+                groups: int_list_val_parser(&mut data, "groups")?,
+            })
+        }
+    }
+
+    #[test]
+    fn verify_parse_grouped() {
+        let input = vec!["id=1 groups=6,8|id=2 groups=7".to_string(), "".to_string()];
+        assert_eq!(
+            vec![
+                Grouped {
+                    id: 1,
+                    groups: vec![6, 8]
+                },
+                Grouped {
+                    id: 2,
+                    groups: vec![7]
+                }
+            ],
+            parse_grouped::<Grouped>(input).unwrap()
         );
+        assert!(parse_grouped::<Grouped>(Vec::new()).unwrap().is_empty());
+        assert!(parse_grouped::<Grouped>(vec!["id=1|id=2 groups=7".to_string()]).is_err());
     }
 }